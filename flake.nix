@@ -58,6 +58,7 @@
               serviceConfig = {
                 Type = "simple";
                 ExecStart = "${cfg.package}/bin/led_matrix_daemon --config=${cfg.configFile}";
+                ExecReload = "${pkgs.coreutils}/bin/kill -HUP $MAINPID";
                 Restart = "on-failure";
                 User = "root";
                 Group = "root";
@@ -1,23 +1,42 @@
-use std::io::Cursor;
+use std::collections::{BTreeMap, HashSet};
 
 use actix_web::{post, web};
-use image::GrayImage;
 use serde::Deserialize;
 use serde_with::base64::Base64;
 use serde_with::serde_as;
 
+use crate::api::decode::decode_gray_image;
 use crate::api::error::ApiError;
+use crate::api::render_task::RenderTarget;
 use crate::api::{AppState, RenderResponse, RenderTask};
+use crate::config::led_matrix_config::LedMatrixConfig;
+use crate::config::matrix_dto::MatrixName;
+use crate::gfx::transition::TransitionOverride;
 
 #[serde_as]
 #[derive(Deserialize, Debug)]
 struct SingleRenderRequest {
+    /// The image for the matrix `left`
     #[serde(default)]
     #[serde_as(as = "Base64")]
     left_image: Option<Vec<u8>>,
+    /// The image for the matrix `right`
     #[serde(default)]
     #[serde_as(as = "Base64")]
     right_image: Option<Vec<u8>>,
+    /// Images by matrix name
+    #[serde(default)]
+    #[serde_as(as = "BTreeMap<_, Base64>")]
+    images: BTreeMap<MatrixName, Vec<u8>>,
+    /// An image spanned across the matrices of `target`
+    #[serde(default)]
+    #[serde_as(as = "Base64")]
+    image: Option<Vec<u8>>,
+    /// A matrix, a group, `all`, or a comma-separated list of them
+    #[serde(default)]
+    target: Option<String>,
+    #[serde(default)]
+    transition: TransitionOverride,
 }
 
 #[derive(Deserialize, Debug)]
@@ -25,22 +44,13 @@ struct MultipleRenderRequest {
     render: Vec<SingleRenderRequest>,
 }
 
-impl SingleRenderRequest {
-    fn buf_to_gray_image(buf: &[u8]) -> Result<GrayImage, ApiError> {
-        let image = image::ImageReader::new(Cursor::new(buf))
-            .with_guessed_format()?
-            .decode()?;
-        Ok(image.into_luma8())
-    }
-}
-
 #[post("/render/base64")]
 pub async fn render_base64(
     render_request: web::Json<SingleRenderRequest>,
     state: web::Data<AppState>,
 ) -> Result<web::Json<RenderResponse>, ApiError> {
-    let task = prepare_task(render_request.into_inner())?;
-    state.sender.send(task).await?;
+    let task = prepare_task(render_request.into_inner(), &state.config())?;
+    state.queue(task).await?;
     Ok(web::Json(RenderResponse {
         queue_len: state.sender.len(),
         queued: true,
@@ -53,8 +63,8 @@ pub async fn render_base64_multiple(
     state: web::Data<AppState>,
 ) -> Result<web::Json<RenderResponse>, ApiError> {
     for request in render_request.into_inner().render {
-        let task = prepare_task(request)?;
-        state.sender.send(task).await?;
+        let task = prepare_task(request, &state.config())?;
+        state.queue(task).await?;
     }
 
     Ok(web::Json(RenderResponse {
@@ -63,23 +73,66 @@ pub async fn render_base64_multiple(
     }))
 }
 
-fn prepare_task(mut render_request: SingleRenderRequest) -> Result<RenderTask, ApiError> {
-    let render_task = match (
-        render_request.left_image.take(),
-        render_request.right_image.take(),
-    ) {
-        (Some(left), Some(right)) => RenderTask::Both(
-            SingleRenderRequest::buf_to_gray_image(&left)?,
-            SingleRenderRequest::buf_to_gray_image(&right)?,
-        ),
-        (Some(left), None) => RenderTask::Left(SingleRenderRequest::buf_to_gray_image(&left)?),
-        (None, Some(right)) => RenderTask::Right(SingleRenderRequest::buf_to_gray_image(&right)?),
-        (None, None) => {
-            return Err(ApiError::BadRequest("No images provided".to_string()));
+fn prepare_task(
+    render_request: SingleRenderRequest,
+    config: &LedMatrixConfig,
+) -> Result<RenderTask, ApiError> {
+    let SingleRenderRequest {
+        left_image,
+        right_image,
+        images: named_images,
+        image,
+        target,
+        transition,
+    } = render_request;
+    let bad_request = |err: anyhow::Error| ApiError::BadRequest(err.to_string());
+
+    let mut images = vec![];
+    let legacy_images = [
+        (MatrixName::left(), left_image),
+        (MatrixName::right(), right_image),
+    ]
+    .into_iter()
+    .filter_map(|(matrix, image)| Some((matrix, image?)));
+    for (matrix, image) in legacy_images.chain(named_images) {
+        if config.port(matrix.as_str()).is_none() {
+            return Err(ApiError::BadRequest(format!(
+                "Matrix {matrix} is not configured"
+            )));
         }
-    };
+        images.push((matrix, decode_gray_image(&image, None)?));
+    }
+
+    match (target, image) {
+        (Some(target), Some(image)) => {
+            let matrices = config.resolve(&target).map_err(bad_request)?;
+            let image = decode_gray_image(&image, None)?;
+            let span = RenderTarget::span(config, matrices, image).map_err(bad_request)?;
+            images.extend(span.into_images());
+        }
+        (None, None) => {}
+        _ => {
+            return Err(ApiError::BadRequest(
+                "target and image must be set together".to_string(),
+            ));
+        }
+    }
 
-    Ok(render_task)
+    if images.is_empty() {
+        return Err(ApiError::BadRequest("No images provided".to_string()));
+    }
+    let mut targeted = HashSet::new();
+    if let Some((matrix, _)) = images.iter().find(|(matrix, _)| !targeted.insert(matrix)) {
+        return Err(ApiError::BadRequest(format!(
+            "Matrix {matrix} is targeted more than once"
+        )));
+    }
+
+    Ok(RenderTask {
+        target: RenderTarget::new(images),
+        transition: transition.apply(config.transition).map_err(bad_request)?,
+        droppable: false,
+    })
 }
 
 #[cfg(test)]
@@ -91,6 +144,11 @@ mod test {
         let request = r#"
         {"left_image":"iVBORw0KGgoAAAANSUhEUgAAAAkAAAAnCAAAAAD4XD2KAAABL0lEQVR4Ae3gAZAkSZIkSRKLqpm7R0REZmZmVlVVVVV3d3d3d/fMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMdHd3d3dXV1VVVVVmZkZGRIS7m5kKz0xmV3d1d3dPz8zMzMxMYuUKqFwBlSv4qcIVX6wCAO/bpgLAa03DqgA8fBpWRwJO9H3f94JZqbXUIiIiIiLqRhwAQCwWAEBsHwMA4sy1AEDc9CAAoD5kGwCoDzkBANSbTgMA9cw1AIC491oAEFdA5QqoXAGVK6ByBVSugMoVULkCKldA5QqoXAGVK6ByBVSugMoVULkCKldA5QqoXAH/CPdDDvUGNwlOAAAAAElFTkSuQmCC"}
         "#;
-        assert!(serde_json::from_str::<SingleRenderRequest>(request).is_ok())
+        assert!(serde_json::from_str::<SingleRenderRequest>(request).is_ok());
+
+        let request = r#"{"images": {"desk": "AAEC"}, "target": "all", "image": "AAEC"}"#;
+        let request = serde_json::from_str::<SingleRenderRequest>(request).unwrap();
+        assert_eq!(request.images[&MatrixName::from("desk")], [0, 1, 2]);
+        assert_eq!(request.image, Some(vec![0, 1, 2]));
     }
 }
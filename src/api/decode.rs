@@ -0,0 +1,54 @@
+use std::io::Cursor;
+
+use image::GrayImage;
+
+use crate::api::error::ApiError;
+
+pub const SVG_MIME: &str = "image/svg+xml";
+
+/// Decodes an uploaded image into a grayscale bitmap. SVG documents are detected either by
+/// the declared content type or by sniffing the payload, and are rasterised to the matrix geometry.
+pub fn decode_gray_image(buf: &[u8], content_type: Option<&str>) -> Result<GrayImage, ApiError> {
+    if content_type == Some(SVG_MIME) || is_svg(buf) {
+        return decode_svg(buf);
+    }
+
+    let image = image::ImageReader::new(Cursor::new(buf))
+        .with_guessed_format()?
+        .decode()?;
+    Ok(image.into_luma8())
+}
+
+fn is_svg(buf: &[u8]) -> bool {
+    let head = &buf[..buf.len().min(1024)];
+    let head = String::from_utf8_lossy(head);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    (head.starts_with("<?xml") || head.starts_with("<svg") || head.starts_with("<!--"))
+        && head.contains("<svg")
+}
+
+#[cfg(feature = "svg")]
+fn decode_svg(buf: &[u8]) -> Result<GrayImage, ApiError> {
+    use crate::hw::{HEIGHT, WIDTH};
+    crate::api::svg::rasterize(buf, WIDTH as u32, HEIGHT as u32)
+}
+
+#[cfg(not(feature = "svg"))]
+fn decode_svg(_buf: &[u8]) -> Result<GrayImage, ApiError> {
+    Err(ApiError::BadRequest(
+        "SVG support is not enabled in this build".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_svg() {
+        assert!(is_svg(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"));
+        assert!(is_svg(b"\n  <?xml version=\"1.0\"?>\n<svg/>"));
+        assert!(!is_svg(b"<?xml version=\"1.0\"?><html/>"));
+        assert!(!is_svg(&std::fs::read("test_data/img0.png").unwrap()));
+    }
+}
@@ -10,6 +10,9 @@ pub enum ApiError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     #[error("Internal error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -21,6 +24,10 @@ pub enum ApiError {
 
     #[error("Send error: {0}")]
     SendError(#[from] kanal::SendError),
+
+    #[cfg(feature = "svg")]
+    #[error("SVG error: {0}")]
+    SvgError(#[from] resvg::usvg::Error),
 }
 
 #[derive(Debug, Serialize)]
@@ -33,12 +40,15 @@ impl ResponseError for ApiError {
         match &self {
             Self::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
 
             // treat IoError as BadRequest
             ApiError::IoError(_) => StatusCode::BAD_REQUEST,
             ApiError::ImageError(_) => StatusCode::BAD_REQUEST,
             ApiError::JoinError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::SendError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            #[cfg(feature = "svg")]
+            ApiError::SvgError(_) => StatusCode::BAD_REQUEST,
         }
     }
     fn error_response(&self) -> HttpResponse {
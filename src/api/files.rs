@@ -4,16 +4,34 @@ use actix_web::{post, web};
 use anyhow::anyhow;
 use futures_util::{StreamExt, TryStreamExt};
 use image::GrayImage;
-use kanal::AsyncSender;
+use serde::Deserialize;
 
+use crate::api::decode::decode_gray_image;
 use crate::api::error::ApiError;
+use crate::api::render_task::RenderTarget;
 use crate::api::{AppState, RenderResponse, RenderTask};
+use crate::config::led_matrix_config::LedMatrixConfig;
+use crate::gfx::transition::TransitionOverride;
+use crate::hw::device_kind::DeviceKind;
+
+#[derive(Debug, Deserialize)]
+pub struct FilesQuery {
+    /// Spans each file across the matrices of a target instead of distributing the files
+    #[serde(default)]
+    target: Option<String>,
+}
 
 #[post("/render/files")]
 pub async fn render_files(
     mut payload: Multipart,
+    query: web::Query<FilesQuery>,
+    transition: web::Query<TransitionOverride>,
     state: web::Data<AppState>,
 ) -> Result<Json<RenderResponse>, ApiError> {
+    let config = state.config();
+    let transition = transition
+        .apply(config.transition)
+        .map_err(|err| ApiError::BadRequest(err.to_string()))?;
     let mut images = vec![];
 
     while let Some(mut field) = payload
@@ -21,36 +39,40 @@ pub async fn render_files(
         .await
         .map_err(|err| anyhow!("Multipart error: {err:?}"))?
     {
+        let content_type = field
+            .content_type()
+            .map(|mime| mime.essence_str().to_string());
         let mut file_data = BytesMut::new();
         while let Some(chunk) = field.next().await {
             let data = chunk.map_err(|err| anyhow!("Multipart error: {err:?}"))?;
             file_data.extend_from_slice(&data);
         }
 
-        let image = image::load_from_memory(&file_data)?.into_luma8();
+        let image = decode_gray_image(&file_data, content_type.as_deref())?;
         images.push(image);
     }
 
-    match (
-        state.config.left_port.as_ref(),
-        state.config.right_port.as_ref(),
-    ) {
-        (Some(_), Some(_)) => {
-            queue_even_odd(images, &state.sender).await?;
-        }
-        (Some(_), None) => {
-            for image in images {
-                state.sender.send(RenderTask::Left(image)).await?;
-            }
-        }
-        (None, Some(_)) => {
-            for image in images {
-                state.sender.send(RenderTask::Right(image)).await?;
-            }
-        }
-        (None, None) => {
-            return Err(ApiError::InternalError(anyhow!("No ports configured")));
+    let targets = match query.target.as_deref() {
+        Some(target) => {
+            let matrices = config
+                .resolve(target)
+                .map_err(|err| ApiError::BadRequest(err.to_string()))?;
+            images
+                .into_iter()
+                .map(|image| RenderTarget::span(&config, matrices.clone(), image))
+                .collect::<anyhow::Result<Vec<_>>>()
+                .map_err(|err| ApiError::BadRequest(err.to_string()))?
         }
+        None => distribute(images, &config)?,
+    };
+    for target in targets {
+        state
+            .queue(RenderTask {
+                target,
+                transition,
+                droppable: false,
+            })
+            .await?;
     }
 
     Ok(Json(RenderResponse {
@@ -59,19 +81,29 @@ pub async fn render_files(
     }))
 }
 
-async fn queue_even_odd(
+/// Hands the images to the LED matrices in order, one per matrix, and starts over with the
+/// first matrix once each has one. Other modules take different sizes and need a target.
+fn distribute(
     images: Vec<GrayImage>,
-    sender: &AsyncSender<RenderTask>,
-) -> anyhow::Result<()> {
-    let mut iter = images.into_iter();
-
-    while let (Some(left), Some(right)) = (iter.next(), iter.next()) {
-        sender.send(RenderTask::Both(left, right)).await?;
+    config: &LedMatrixConfig,
+) -> Result<Vec<RenderTarget>, ApiError> {
+    let matrices = config
+        .matrices
+        .iter()
+        .filter(|port| port.kind() == DeviceKind::LedMatrix)
+        .map(|port| port.name().clone())
+        .collect::<Vec<_>>();
+    if matrices.is_empty() {
+        return Err(ApiError::InternalError(anyhow!(
+            "No LED matrices configured"
+        )));
     }
 
-    if let Some(left) = iter.next() {
-        sender.send(RenderTask::Left(left)).await?;
+    let mut targets = vec![];
+    let mut images = images.into_iter().peekable();
+    while images.peek().is_some() {
+        let matrices = matrices.iter().cloned();
+        targets.push(RenderTarget::new(matrices.zip(images.by_ref()).collect()));
     }
-
-    Ok(())
+    Ok(targets)
 }
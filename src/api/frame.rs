@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use actix_web::web::Json;
+use actix_web::{get, web, HttpResponse};
+use image::imageops::FilterType;
+use image::{GrayImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::api::error::ApiError;
+use crate::api::AppState;
+use crate::config::matrix_dto::MatrixName;
+
+const MAX_SCALE: u32 = 32;
+
+#[derive(Debug, Deserialize)]
+pub struct FrameQuery {
+    #[serde(default = "default_scale")]
+    scale: u32,
+}
+
+fn default_scale() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize)]
+pub struct FrameDto {
+    width: u32,
+    height: u32,
+    /// Brightness values, row by row
+    rows: Vec<Vec<u8>>,
+}
+
+impl From<&GrayImage> for FrameDto {
+    fn from(value: &GrayImage) -> Self {
+        FrameDto {
+            width: value.width(),
+            height: value.height(),
+            rows: value
+                .rows()
+                .map(|row| row.map(|pixel| pixel.0[0]).collect())
+                .collect(),
+        }
+    }
+}
+
+/// The frames side by side, in order; matrices that have not displayed anything are left out.
+fn span(frames: Vec<Option<GrayImage>>) -> Option<GrayImage> {
+    let frames = frames.into_iter().flatten().collect::<Vec<_>>();
+    if frames.is_empty() {
+        return None;
+    }
+    let width = frames.iter().map(GrayImage::width).sum();
+    let height = frames
+        .iter()
+        .map(GrayImage::height)
+        .max()
+        .unwrap_or_default();
+
+    let mut span = GrayImage::new(width, height);
+    let mut x = 0;
+    for frame in frames {
+        image::imageops::replace(&mut span, &frame, x, 0);
+        x += frame.width() as i64;
+    }
+    Some(span)
+}
+
+/// A target is a matrix, a group, `span` for all matrices, or a comma-separated list of them.
+#[get("/frame/{target}")]
+pub async fn get_frame(
+    target: web::Path<String>,
+    query: web::Query<FrameQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    if !(1..=MAX_SCALE).contains(&query.scale) {
+        return Err(ApiError::BadRequest(format!(
+            "Scale must be between 1 and {MAX_SCALE}"
+        )));
+    }
+
+    let config = state.config();
+    let matrices = config
+        .resolve(&target)
+        .map_err(|err| ApiError::NotFound(err.to_string()))?;
+    let frames = matrices
+        .iter()
+        .map(|matrix| config.port(matrix.as_str()).and_then(|port| port.frame()))
+        .collect();
+    let image = span(frames)
+        .ok_or_else(|| ApiError::NotFound("Nothing has been displayed yet".to_string()))?;
+
+    let image = if query.scale == 1 {
+        image
+    } else {
+        let (width, height) = image.dimensions();
+        image::imageops::resize(
+            &image,
+            width * query.scale,
+            height * query.scale,
+            FilterType::Nearest,
+        )
+    };
+
+    let mut png = Cursor::new(vec![]);
+    image.write_to(&mut png, ImageFormat::Png)?;
+    Ok(HttpResponse::Ok()
+        .content_type("image/png")
+        .body(png.into_inner()))
+}
+
+/// The frames by matrix name. `left` and `right` are always there, as they were before
+/// matrices could be named, and are `null` if they are not configured.
+#[get("/frame.json")]
+pub async fn get_frames_json(
+    state: web::Data<AppState>,
+) -> Json<BTreeMap<MatrixName, Option<FrameDto>>> {
+    let mut frames = BTreeMap::from([(MatrixName::left(), None), (MatrixName::right(), None)]);
+    for port in state.config().matrices.iter() {
+        let frame = port.frame();
+        frames.insert(port.name().clone(), frame.as_ref().map(FrameDto::from));
+    }
+    Json(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+
+    #[test]
+    fn test_span() {
+        let left = GrayImage::from_pixel(9, 34, Luma([10]));
+        let right = GrayImage::from_pixel(9, 34, Luma([20]));
+
+        let all = span(vec![
+            Some(left.clone()),
+            None,
+            Some(right.clone()),
+            Some(left.clone()),
+        ]);
+        let all = all.unwrap();
+        assert_eq!(all.dimensions(), (27, 34));
+        assert_eq!(all.get_pixel(8, 0), &Luma([10]));
+        assert_eq!(all.get_pixel(9, 33), &Luma([20]));
+        assert_eq!(all.get_pixel(18, 0), &Luma([10]));
+
+        assert_eq!(span(vec![Some(left.clone()), None]), Some(left));
+        assert_eq!(span(vec![None, None]), None);
+    }
+}
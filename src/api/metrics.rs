@@ -0,0 +1,22 @@
+use std::collections::HashMap;
+
+use actix_web::web::Json;
+use actix_web::{get, web};
+
+use crate::api::AppState;
+use crate::config::matrix_dto::MatrixName;
+use crate::hw::metrics::PortMetricsSnapshot;
+
+#[get("/metrics")]
+pub async fn port_metrics(
+    state: web::Data<AppState>,
+) -> Json<HashMap<MatrixName, PortMetricsSnapshot>> {
+    Json(
+        state
+            .config()
+            .matrices
+            .iter()
+            .map(|port| (port.name().clone(), port.metrics().snapshot()))
+            .collect(),
+    )
+}
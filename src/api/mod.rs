@@ -2,18 +2,41 @@ use std::sync::Arc;
 
 use serde::Serialize;
 
+use crate::api::error::ApiError;
 use crate::api::render_task::RenderTask;
-use crate::config::led_matrix_config::LedMatrixConfig;
+use crate::config::led_matrix_config::{LedMatrixConfig, SharedConfig};
 
 pub mod base64;
+pub mod decode;
 mod error;
 pub mod files;
-mod render_task;
+pub mod frame;
+pub mod metrics;
+pub mod render_task;
+#[cfg(feature = "svg")]
+mod svg;
+pub mod widgets;
 
 #[derive(Debug)]
 pub struct AppState {
     pub sender: kanal::AsyncSender<RenderTask>,
-    pub config: Arc<LedMatrixConfig>,
+    pub config: Arc<SharedConfig>,
+}
+
+impl AppState {
+    pub fn config(&self) -> Arc<LedMatrixConfig> {
+        self.config.current()
+    }
+
+    /// Queues an explicit render request, which suspends widgets on the affected matrices.
+    pub async fn queue(&self, task: RenderTask) -> Result<(), ApiError> {
+        let config = self.config();
+        for matrix in task.target.matrices() {
+            config.holds.hold(matrix);
+        }
+        self.sender.send(task).await?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -1,80 +1,366 @@
-use std::io::ErrorKind;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use crate::config::led_matrix_config::LedMatrixConfig;
-use crate::hw::port::Port;
+use crate::config::matrix_dto::MatrixName;
+use crate::gfx::transition::Transition;
+use crate::hw::worker::PortWorker;
 use anyhow::{anyhow, bail};
-use futures_util::join;
+use futures_util::future::{join_all, BoxFuture};
 use futures_util::FutureExt;
 use image::GrayImage;
-use tokio::task::JoinHandle;
-use tracing::error;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinSet;
 
+/// The image for each targeted matrix
 #[derive(Debug)]
-pub enum RenderTask {
-    Left(GrayImage),
-    Right(GrayImage),
-    Both(GrayImage, GrayImage),
+pub struct RenderTarget {
+    images: Vec<(MatrixName, GrayImage)>,
 }
 
-impl RenderTask {
-    fn spawn_blocking_render_port(
-        port: Arc<Mutex<Port>>,
+impl RenderTarget {
+    pub fn new(images: Vec<(MatrixName, GrayImage)>) -> Self {
+        RenderTarget { images }
+    }
+
+    pub fn single(matrix: MatrixName, image: GrayImage) -> Self {
+        RenderTarget::new(vec![(matrix, image)])
+    }
+
+    /// Splits the image horizontally across the matrices, in order. The image for a single
+    /// matrix is passed on as it is.
+    pub fn span(
+        config: &LedMatrixConfig,
+        matrices: Vec<MatrixName>,
         image: GrayImage,
-    ) -> JoinHandle<anyhow::Result<()>> {
-        tokio::task::spawn_blocking(move || {
-            let mut port = port
-                .lock()
-                .map_err(|err| anyhow!("Poisoned mutex: {err:?}"))?;
-
-            // We return ErrorKind::Other ourselves: stdlib does not use it, so we know that
-            // something wrong with the port has happened, and we'll try our luck and release
-            // the handle, so we would not interfere with kernel device numbering
-            match port.display_gray_image(image) {
-                Err(err) if err.kind() != ErrorKind::Other => {
-                    error!(?err, ?port, "Shutting down the port");
-                    port.close();
-                }
-                _ => {}
-            }
-            Ok(())
-        })
+    ) -> anyhow::Result<Self> {
+        if let [matrix] = matrices.as_slice() {
+            return Ok(RenderTarget::single(matrix.clone(), image));
+        }
+
+        let sizes = matrices
+            .iter()
+            .map(|matrix| {
+                config
+                    .port(matrix.as_str())
+                    .map(PortWorker::size)
+                    .ok_or_else(|| anyhow!("{matrix} port is not configured"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let width = sizes.iter().map(|(width, _)| width).sum::<u32>();
+        let height = sizes.first().map_or(0, |(_, height)| *height);
+        if sizes.iter().any(|(_, other)| *other != height) {
+            bail!("Matrices of different heights cannot be spanned");
+        }
+        if image.dimensions() != (width, height) {
+            bail!(
+                "Image must be {width}x{height} pixels to span {} matrices; got {}x{}",
+                matrices.len(),
+                image.width(),
+                image.height()
+            );
+        }
+
+        let mut x = 0;
+        let images = matrices
+            .into_iter()
+            .zip(sizes)
+            .map(|(matrix, (width, height))| {
+                let part = image::imageops::crop_imm(&image, x, 0, width, height).to_image();
+                x += width;
+                (matrix, part)
+            })
+            .collect();
+        Ok(RenderTarget::new(images))
     }
-    pub async fn render(self, config: Arc<LedMatrixConfig>) -> anyhow::Result<()> {
-        match self {
-            RenderTask::Left(left) => {
-                if let Some(port) = config.left_port.as_ref() {
-                    Self::spawn_blocking_render_port(port.clone(), left).await??;
-                } else {
-                    bail!("Left port is not configured");
-                }
-            }
 
-            RenderTask::Right(right) => {
-                if let Some(port) = config.right_port.as_ref() {
-                    Self::spawn_blocking_render_port(port.clone(), right).await??;
-                } else {
-                    bail!("Right port is not configured");
-                }
-            }
+    pub fn into_images(self) -> Vec<(MatrixName, GrayImage)> {
+        self.images
+    }
 
-            RenderTask::Both(left, right) => {
-                match (config.left_port.as_ref(), config.right_port.as_ref()) {
-                    (Some(left_port), Some(right_port)) => {
-                        let (left_result, right_result) = join! {
-                            Self::spawn_blocking_render_port(left_port.clone(), left).fuse(),
-                            Self::spawn_blocking_render_port(right_port.clone(), right).fuse(),
-                        };
-                        left_result??;
-                        right_result??;
-                    }
-                    (None, Some(_)) => bail!("Left port is not configured"),
-                    (Some(_), None) => bail!("Right port is not configured"),
-                    (None, None) => bail!("Both ports are not configured"),
+    pub fn matrices(&self) -> impl Iterator<Item = &MatrixName> {
+        self.images.iter().map(|(matrix, _)| matrix)
+    }
+}
+
+#[derive(Debug)]
+pub struct RenderTask {
+    pub target: RenderTarget,
+    pub transition: Transition,
+    /// Widget frames are dropped if a port queue is full, since they would be stale by the
+    /// time there is room; everything else waits for room in the lane of its port.
+    pub droppable: bool,
+}
+
+impl RenderTask {
+    /// Hands every image of the task to the lane of its port without waiting, so one stuck
+    /// port never holds up the others. The returned future resolves to the result of each
+    /// port once the images are displayed. Ports do not wait for each other, so a task
+    /// targeting both of them may succeed on one port only.
+    pub fn submit(
+        self,
+        config: &LedMatrixConfig,
+        lanes: &mut PortLanes,
+    ) -> BoxFuture<'static, Vec<(MatrixName, anyhow::Result<()>)>> {
+        lanes.retain(config);
+        let mut pending = vec![];
+        for (matrix, image) in self.target.into_images() {
+            let port = config.matrices.iter().find(|port| port.name() == &matrix);
+            let queued = match port {
+                None => Err(anyhow!("{matrix} port is not configured")),
+                Some(port) => {
+                    let (reply, queued) = oneshot::channel();
+                    let share = Share {
+                        image,
+                        transition: self.transition,
+                        droppable: self.droppable,
+                        reply,
+                    };
+                    lanes
+                        .queue(port, config.max_queue_size, share)
+                        .map(|()| queued)
                 }
+            };
+            pending.push((matrix, queued));
+        }
+
+        join_all(pending.into_iter().map(|(matrix, queued)| async move {
+            let result = match queued {
+                Ok(queued) => match queued.await {
+                    Ok(Ok(display)) => display.await,
+                    Ok(Err(err)) => Err(err),
+                    Err(_) => Err(anyhow!("{matrix} port lane has stopped")),
+                },
+                Err(err) => Err(err),
+            };
+            (matrix, result)
+        }))
+        .boxed()
+    }
+}
+
+type Display = BoxFuture<'static, anyhow::Result<()>>;
+
+/// The part of a render task for one port
+struct Share {
+    image: GrayImage,
+    transition: Transition,
+    droppable: bool,
+    reply: oneshot::Sender<anyhow::Result<Display>>,
+}
+
+/// A queue of `max_queue_size` shares in front of every port queue. Shares wait in the lane
+/// of their port for room in the port queue, so a stuck port only holds up its own frames;
+/// once its lane is full as well, further frames for it are dropped.
+#[derive(Debug, Default)]
+pub struct PortLanes {
+    lanes: Vec<(Arc<PortWorker>, mpsc::Sender<Share>)>,
+    tasks: JoinSet<()>,
+}
+
+impl PortLanes {
+    fn queue(
+        &mut self,
+        port: &Arc<PortWorker>,
+        capacity: usize,
+        share: Share,
+    ) -> anyhow::Result<()> {
+        let index = match self
+            .lanes
+            .iter()
+            .position(|(lane_port, _)| Arc::ptr_eq(lane_port, port))
+        {
+            Some(index) => index,
+            None => {
+                let (sender, receiver) = mpsc::channel(capacity.max(1));
+                self.tasks.spawn(Self::run(port.clone(), receiver));
+                self.lanes.push((port.clone(), sender));
+                self.lanes.len() - 1
             }
+        };
+
+        if self.lanes[index].1.try_send(share).is_err() {
+            port.metrics().record_dropped();
+            bail!("{} port is not keeping up, dropped the frame", port.name());
         }
+        Ok(())
+    }
+
+    async fn run(port: Arc<PortWorker>, mut receiver: mpsc::Receiver<Share>) {
+        while let Some(share) = receiver.recv().await {
+            let display = if share.droppable {
+                port.submit(share.image, share.transition)
+                    .map(FutureExt::boxed)
+            } else {
+                port.submit_waiting(share.image, share.transition)
+                    .await
+                    .map(FutureExt::boxed)
+            };
+            let _ = share.reply.send(display);
+        }
+    }
+
+    /// Closes the lanes of ports that `config` does not use anymore; they still hand what
+    /// they hold to their ports.
+    fn retain(&mut self, config: &LedMatrixConfig) {
+        self.lanes.retain(|(port, _)| {
+            config
+                .matrices
+                .iter()
+                .any(|current| Arc::ptr_eq(port, current))
+        });
+        while self.tasks.try_join_next().is_some() {}
+    }
+
+    /// Waits until every lane has handed its shares to its port.
+    pub async fn finish(mut self) {
+        self.lanes.clear();
+        while self.tasks.join_next().await.is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use image::Luma;
+
+    use super::*;
+    use crate::hw::transport::mock::MockTransport;
+    use crate::widget::scheduler::RenderHolds;
+
+    fn mock_config() -> (LedMatrixConfig, MockTransport, MockTransport) {
+        let (left_port, left_transport) = PortWorker::mock("left");
+        let (right_port, right_transport) = PortWorker::mock("right");
+        let config = LedMatrixConfig {
+            matrices: vec![Arc::new(left_port), Arc::new(right_port)],
+            groups: HashMap::new(),
+            listen_address: None,
+            unix_socket: None,
+            max_queue_size: 4,
+            num_http_workers: 1,
+            widgets: vec![],
+            schedules: vec![],
+            holds: RenderHolds::new(Duration::ZERO),
+            transition: Transition::default(),
+            state: None,
+            startup_splash: None,
+            startup_splash_duration: Duration::ZERO,
+            shutdown_splash: None,
+            shutdown_timeout: Duration::ZERO,
+            flush_queue_on_shutdown: false,
+            simulate: false,
+            terminal: Arc::default(),
+            sources: Default::default(),
+        };
+        (config, left_transport, right_transport)
+    }
+
+    #[tokio::test]
+    async fn test_partial_success() {
+        let (config, left_transport, right_transport) = mock_config();
+        left_transport.state().fail_with = Some(io::ErrorKind::BrokenPipe);
+        let images = vec![
+            (MatrixName::left(), GrayImage::new(9, 34)),
+            (MatrixName::right(), GrayImage::new(9, 34)),
+        ];
+        let task = RenderTask {
+            target: RenderTarget::new(images),
+            transition: Transition::default(),
+            droppable: false,
+        };
+
+        let mut lanes = PortLanes::default();
+        let results = task.submit(&config, &mut lanes).await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, MatrixName::left());
+        assert!(results[0].1.is_err());
+        assert_eq!(results[1].0, MatrixName::right());
+        assert!(results[1].1.is_ok());
+        assert!(!right_transport.state().written.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_requests_wait_for_room() {
+        let (config, left_transport, _) = mock_config();
+        left_transport.state().write_delay = Some(Duration::from_millis(2));
+
+        // More than the port queue holds; the rest waits in the lane
+        let count = 2 * config.max_queue_size;
+        let mut lanes = PortLanes::default();
+        let mut pending = vec![];
+        for i in 0..count {
+            let image = GrayImage::from_pixel(9, 34, Luma([i as u8 + 1]));
+            let task = RenderTask {
+                target: RenderTarget::single(MatrixName::left(), image),
+                transition: Transition::default(),
+                droppable: false,
+            };
+            pending.push(task.submit(&config, &mut lanes));
+            // like the render loop waiting for the next task
+            tokio::task::yield_now().await;
+        }
+        for results in join_all(pending).await {
+            assert!(results[0].1.is_ok());
+        }
+
+        let commit = [0x32, 0xAC, 0x08];
+        let written = left_transport.state().written.clone();
+        let commits = written.windows(3).filter(|bytes| *bytes == commit).count();
+        assert_eq!(commits, count);
+    }
+
+    #[tokio::test]
+    async fn test_stuck_port_does_not_block_others() {
+        let (config, left_transport, right_transport) = mock_config();
+        left_transport.state().write_delay = Some(Duration::from_secs(1));
+        let task = |matrix: MatrixName, value: u8| RenderTask {
+            target: RenderTarget::single(matrix, GrayImage::from_pixel(9, 34, Luma([value]))),
+            transition: Transition::default(),
+            droppable: false,
+        };
+
+        let mut lanes = PortLanes::default();
+        let mut stuck = vec![];
+        for i in 0..3 * config.max_queue_size {
+            stuck.push(task(MatrixName::left(), i as u8 + 1).submit(&config, &mut lanes));
+        }
+        let right = task(MatrixName::right(), 1).submit(&config, &mut lanes);
+        let results = tokio::time::timeout(Duration::from_millis(500), right)
+            .await
+            .expect("the right port waited for the left one");
+        assert!(results[0].1.is_ok());
+        assert!(!right_transport.state().written.is_empty());
+
+        // Once the lane of the stuck port is full as well, its frames are dropped
+        let dropped = stuck.pop().unwrap().await;
+        assert!(dropped[0].1.is_err());
+        assert!(
+            config
+                .port("left")
+                .unwrap()
+                .metrics()
+                .snapshot()
+                .frames_dropped
+                > 0
+        );
+    }
+
+    #[test]
+    fn test_span() -> anyhow::Result<()> {
+        let (config, _, _) = mock_config();
+        let matrices = config.resolve("right,left")?;
+        let image = GrayImage::from_fn(18, 34, |x, _| Luma([x as u8]));
+
+        let images = RenderTarget::span(&config, matrices.clone(), image)?.into_images();
+        assert_eq!(images[0].0, MatrixName::right());
+        assert_eq!(images[0].1.get_pixel(8, 0), &Luma([8]));
+        assert_eq!(images[1].0, MatrixName::left());
+        assert_eq!(images[1].1.get_pixel(0, 33), &Luma([9]));
 
+        assert!(RenderTarget::span(&config, matrices, GrayImage::new(9, 34)).is_err());
         Ok(())
     }
 }
@@ -0,0 +1,55 @@
+use image::{GrayImage, Luma};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{Options, Tree};
+
+use crate::api::error::ApiError;
+
+/// Rasterises an SVG document into a `width`x`height` grayscale image. The drawing is scaled
+/// to fit preserving its aspect ratio, centered, and composited over black.
+pub fn rasterize(buf: &[u8], width: u32, height: u32) -> Result<GrayImage, ApiError> {
+    let tree = Tree::from_data(buf, &Options::default())?;
+    let size = tree.size();
+
+    let scale = (width as f32 / size.width()).min(height as f32 / size.height());
+    let dx = (width as f32 - size.width() * scale) / 2.0;
+    let dy = (height as f32 - size.height() * scale) / 2.0;
+
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| ApiError::BadRequest(format!("Invalid geometry {width}x{height}")))?;
+    resvg::render(
+        &tree,
+        Transform::from_row(scale, 0.0, 0.0, scale, dx, dy),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia stores premultiplied RGBA, which is exactly the color composited over black
+    let mut image = GrayImage::new(width, height);
+    for (pixel, rgba) in image.pixels_mut().zip(pixmap.pixels()) {
+        let luma =
+            0.2126 * rgba.red() as f32 + 0.7152 * rgba.green() as f32 + 0.0722 * rgba.blue() as f32;
+        *pixel = Luma([luma.round().clamp(0.0, 255.0) as u8]);
+    }
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rasterize() -> anyhow::Result<()> {
+        for name in ["icon0.svg", "icon1.svg"] {
+            let buf = std::fs::read(format!("test_data/{name}"))?;
+            let image = rasterize(&buf, 9, 34)?;
+            assert_eq!(image.dimensions(), (9, 34));
+            assert!(image.pixels().any(|&Luma([p])| p > 0), "{name} is blank");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rasterize_invalid() {
+        assert!(rasterize(b"<svg", 9, 34).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use actix_web::web::Json;
+use actix_web::{get, post, web};
+use serde::Serialize;
+
+use crate::api::error::ApiError;
+use crate::api::AppState;
+use crate::config::matrix_dto::MatrixName;
+use crate::widget::WidgetHandle;
+
+#[derive(Debug, Serialize)]
+pub struct WidgetStatus {
+    name: String,
+    kind: &'static str,
+    port: Option<MatrixName>,
+    enabled: bool,
+    last_error: Option<String>,
+}
+
+impl From<&WidgetHandle> for WidgetStatus {
+    fn from(value: &WidgetHandle) -> Self {
+        WidgetStatus {
+            name: value.name.clone(),
+            kind: value.kind,
+            port: value.matrix.clone(),
+            enabled: value.is_enabled(),
+            last_error: value.last_error(),
+        }
+    }
+}
+
+#[get("/widgets")]
+pub async fn list_widgets(state: web::Data<AppState>) -> Json<Vec<WidgetStatus>> {
+    Json(
+        state
+            .config()
+            .widgets
+            .iter()
+            .map(|widget| WidgetStatus::from(widget.as_ref()))
+            .collect(),
+    )
+}
+
+#[post("/widgets/{name}/enable")]
+pub async fn enable_widget(
+    name: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<Json<WidgetStatus>, ApiError> {
+    set_widget_enabled(&name, true, &state)
+}
+
+#[post("/widgets/{name}/disable")]
+pub async fn disable_widget(
+    name: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<Json<WidgetStatus>, ApiError> {
+    set_widget_enabled(&name, false, &state)
+}
+
+fn set_widget_enabled(
+    name: &str,
+    enabled: bool,
+    state: &AppState,
+) -> Result<Json<WidgetStatus>, ApiError> {
+    let config = state.config();
+    let widget = config
+        .widget(name)
+        .ok_or_else(|| ApiError::NotFound(format!("Widget {name} does not exist")))?;
+    widget.set_enabled(enabled);
+    Ok(Json(WidgetStatus::from(widget.as_ref())))
+}
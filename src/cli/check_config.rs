@@ -0,0 +1,80 @@
+use std::fmt::Display;
+use std::path::Path;
+use std::process::ExitCode;
+
+use crate::cli::cmd_args::ConfigOverrides;
+use crate::config::key_path::KeyPath;
+use crate::config::led_matrix_config::LedMatrixConfig;
+use crate::init::log_directives;
+
+/// Reports every problem found in the configuration file. The widgets, schedules and splashes
+/// are checked without starting any ports, so the devices are left alone.
+pub fn check_config(
+    path: &Path,
+    overrides: &ConfigOverrides,
+    log_level: Option<&str>,
+    print: bool,
+) -> ExitCode {
+    let config = match overrides.load(path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // only to point at lines, the file has just been read successfully
+    let source = std::fs::read_to_string(path).unwrap_or_default();
+    let locate = |key: &KeyPath, message: &dyn Display| match key.line(&source) {
+        Some(line) => format!("line {line}: {key}: {message}"),
+        None => format!("{key}: {message}"),
+    };
+
+    let mut problems: Vec<String> = config
+        .problems()
+        .iter()
+        .map(|problem| locate(&problem.key, &problem.message))
+        .collect();
+    if !config.simulate {
+        for (key, matrix) in config.keyed_matrices() {
+            if !Path::new(&matrix.port.path).exists() {
+                let message = format!(
+                    "Matrix {} path {} does not exist",
+                    matrix.name, matrix.port.path
+                );
+                problems.push(locate(&key.key("path"), &message));
+            }
+        }
+    }
+
+    if print {
+        match toml::to_string_pretty(&config) {
+            Ok(effective) => {
+                // not part of the file, so a comment keeps the output a valid configuration
+                println!("# log level: {}\n{effective}", log_directives(log_level));
+            }
+            Err(err) => problems.push(format!("Failed to print the configuration: {err}")),
+        }
+    }
+
+    if problems.is_empty() {
+        if let Err(err) = LedMatrixConfig::check(config) {
+            problems.push(match err.downcast_ref::<KeyPath>() {
+                Some(key) => {
+                    let causes: Vec<String> = err.chain().skip(1).map(|c| c.to_string()).collect();
+                    locate(key, &causes.join(": "))
+                }
+                None => format!("{err:#}"),
+            });
+        }
+    }
+
+    if problems.is_empty() {
+        eprintln!("{}: OK", path.display());
+        return ExitCode::SUCCESS;
+    }
+    for problem in problems {
+        eprintln!("error: {problem}");
+    }
+    ExitCode::FAILURE
+}
@@ -1,11 +1,176 @@
-use std::path::PathBuf;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use clap::builder::BoolishValueParser;
+use clap::{Args, Parser, Subcommand};
+
+use crate::config::lef_matrix_config_dto::LedMatrixConfigDto;
+use crate::config::port_dto::PortDto;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = r###"led_matrix_daemon"###)]
 pub struct CmdArgs {
     /// Path to the configuration file.
-    #[arg(short, long, default_value = "/etc/led_matrix/daemon.toml")]
+    #[arg(
+        short,
+        long,
+        env = "LED_MATRIX_CONFIG",
+        default_value = "/etc/led_matrix/daemon.toml"
+    )]
     pub config: PathBuf,
+
+    /// Log level or filter directives, takes precedence over `RUST_LOG`.
+    #[arg(long, env = "LED_MATRIX_LOG_LEVEL")]
+    pub log_level: Option<String>,
+
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Values that take precedence over the configuration file. A flag takes precedence over
+/// its environment variable.
+#[derive(Args, Debug)]
+pub struct ConfigOverrides {
+    /// Draw the matrices in the terminal instead of writing to the serial ports;
+    /// `--simulate=false` turns off `simulate` from the configuration file.
+    #[arg(
+        long,
+        env = "LED_MATRIX_SIMULATE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        value_parser = BoolishValueParser::new()
+    )]
+    pub simulate: Option<bool>,
+
+    #[arg(long, env = "LED_MATRIX_LISTEN_ADDRESS")]
+    pub listen_address: Option<SocketAddr>,
+
+    #[arg(long, env = "LED_MATRIX_UNIX_SOCKET")]
+    pub unix_socket: Option<String>,
+
+    #[arg(long, env = "LED_MATRIX_MAX_QUEUE_SIZE")]
+    pub max_queue_size: Option<usize>,
+
+    /// Serial port of the matrix `left`; added with the default settings if not configured.
+    #[arg(long, env = "LED_MATRIX_LEFT_PORT")]
+    pub left_port: Option<String>,
+
+    /// Serial port of the matrix `right`; added with the default settings if not configured.
+    #[arg(long, env = "LED_MATRIX_RIGHT_PORT")]
+    pub right_port: Option<String>,
+}
+
+impl ConfigOverrides {
+    /// Reads the configuration file and applies the overrides to it.
+    pub fn load(&self, path: &Path) -> anyhow::Result<LedMatrixConfigDto> {
+        let mut config = LedMatrixConfigDto::try_from(path)?;
+        self.apply(&mut config);
+        Ok(config)
+    }
+
+    fn apply(&self, config: &mut LedMatrixConfigDto) {
+        if let Some(simulate) = self.simulate {
+            config.simulate = simulate;
+        }
+        if let Some(listen_address) = self.listen_address {
+            config.listen_address = Some(listen_address);
+        }
+        if let Some(unix_socket) = self.unix_socket.as_ref() {
+            config.unix_socket = Some(unix_socket.clone());
+        }
+        if let Some(max_queue_size) = self.max_queue_size {
+            config.max_queue_size = max_queue_size;
+        }
+        for (name, port, path) in [
+            ("left", &mut config.left_port, &self.left_port),
+            ("right", &mut config.right_port, &self.right_port),
+        ] {
+            let Some(path) = path else {
+                continue;
+            };
+            let matrix = config
+                .matrices
+                .iter_mut()
+                .find(|matrix| matrix.name.as_str() == name);
+            match (port, matrix) {
+                (Some(port), _) => port.path = path.clone(),
+                (None, Some(matrix)) => matrix.port.path = path.clone(),
+                (port, None) => *port = Some(PortDto::new(path.clone())),
+            }
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check the configuration file and exit, without touching the devices.
+    CheckConfig {
+        /// Path to the configuration file, `--config` if not set.
+        path: Option<PathBuf>,
+
+        /// Print the effective configuration with the defaults and overrides filled in.
+        #[arg(long)]
+        print: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides() -> anyhow::Result<()> {
+        let args = CmdArgs::try_parse_from([
+            "led_matrix_daemon",
+            "--max-queue-size=5",
+            "--right-port=/dev/ttyACM3",
+            "--left-port=/dev/ttyACM2",
+        ])?;
+        let mut config: LedMatrixConfigDto = toml::from_str(
+            r#"
+            listen_address = "127.0.0.1:45935"
+            max_queue_size = 10
+
+            [left_port]
+            path = "/dev/ttyACM0"
+            rotation = 180
+            "#,
+        )?;
+        args.overrides.apply(&mut config);
+
+        assert_eq!(config.max_queue_size, 5);
+        let left_port = config.left_port.unwrap();
+        assert_eq!(
+            (left_port.path.as_str(), left_port.rotation),
+            ("/dev/ttyACM2", 180)
+        );
+        assert_eq!(
+            config.right_port,
+            Some(PortDto::new("/dev/ttyACM3".to_string()))
+        );
+        assert!(config.listen_address.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_simulate_override() -> anyhow::Result<()> {
+        let simulate = |args: &[&str], simulate: bool| -> anyhow::Result<bool> {
+            let args = CmdArgs::try_parse_from([&["led_matrix_daemon"], args].concat())?;
+            let mut config: LedMatrixConfigDto = toml::from_str(&format!(
+                "listen_address = \"127.0.0.1:45935\"\nsimulate = {simulate}"
+            ))?;
+            args.overrides.apply(&mut config);
+            Ok(config.simulate)
+        };
+
+        assert!(simulate(&[], true)?);
+        assert!(simulate(&["--simulate"], false)?);
+        assert!(!simulate(&["--simulate=false"], true)?);
+        assert!(!simulate(&["--simulate=0", "check-config"], true)?);
+        Ok(())
+    }
 }
@@ -1 +1,2 @@
+pub mod check_config;
 pub mod cmd_args;
@@ -0,0 +1,135 @@
+use std::fmt::{Display, Formatter};
+
+use toml_edit::{ImDocument, Item};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Where a setting lives in the configuration file, e.g. `widgets[2].interval`. Attached to
+/// configuration errors as context, so `check-config` can point at the offending line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPath(Vec<Segment>);
+
+impl KeyPath {
+    pub fn new(key: impl Into<String>) -> Self {
+        KeyPath(vec![Segment::Key(key.into())])
+    }
+
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.0.push(Segment::Key(key.into()));
+        self
+    }
+
+    pub fn index(mut self, index: usize) -> Self {
+        self.0.push(Segment::Index(index));
+        self
+    }
+
+    /// The line of the deepest part of the path found in `source`, starting at 1.
+    pub fn line(&self, source: &str) -> Option<usize> {
+        let document = ImDocument::parse(source).ok()?;
+        let mut item = document.as_item();
+        let mut span = None;
+        for segment in &self.0 {
+            let (key_span, next): (_, &Item) = match segment {
+                Segment::Key(key) => {
+                    let Some((key, next)) = item
+                        .as_table_like()
+                        .and_then(|table| table.get_key_value(key))
+                    else {
+                        break;
+                    };
+                    (key.span(), next)
+                }
+                Segment::Index(index) => match item.get(*index) {
+                    Some(next) => (None, next),
+                    None => break,
+                },
+            };
+            span = key_span.or_else(|| next.span()).or(span);
+            item = next;
+        }
+        let start = span?.start;
+        Some(source.get(..start)?.matches('\n').count() + 1)
+    }
+}
+
+impl From<&serde_ignored::Path<'_>> for KeyPath {
+    fn from(value: &serde_ignored::Path<'_>) -> Self {
+        use serde_ignored::Path;
+        match value {
+            Path::Root => KeyPath(vec![]),
+            Path::Seq { parent, index } => KeyPath::from(*parent).index(*index),
+            Path::Map { parent, key } => KeyPath::from(*parent).key(key.as_str()),
+            Path::Some { parent }
+            | Path::NewtypeStruct { parent }
+            | Path::NewtypeVariant { parent } => KeyPath::from(*parent),
+        }
+    }
+}
+
+impl Display for KeyPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (position, segment) in self.0.iter().enumerate() {
+            match segment {
+                Segment::Index(index) => write!(f, "[{index}]")?,
+                Segment::Key(key) => {
+                    if position > 0 {
+                        write!(f, ".")?;
+                    }
+                    let bare = !key.is_empty()
+                        && key
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                    if bare {
+                        write!(f, "{key}")?;
+                    } else {
+                        write!(f, "{key:?}")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let source = r#"
+            max_queue_size = 0
+
+            [[widgets]]
+            name = "clock"
+            kind = "clock"
+
+            [[widgets]]
+            name = "cpu"
+            kind = "cpu"
+            interval = "0s"
+
+            [groups]
+            "a,b" = ["left"]
+            "#;
+
+        let interval = KeyPath::new("widgets").index(1).key("interval");
+        assert_eq!(interval.to_string(), "widgets[1].interval");
+        assert_eq!(interval.line(source), Some(11));
+        assert_eq!(KeyPath::new("max_queue_size").line(source), Some(2));
+        let group = KeyPath::new("groups").key("a,b");
+        assert_eq!(group.to_string(), "groups.\"a,b\"");
+        assert_eq!(group.line(source), Some(14));
+        // the widget, since it has no port
+        assert_eq!(
+            KeyPath::new("widgets").index(0).key("port").line(source),
+            Some(4)
+        );
+        assert_eq!(KeyPath::new("state").line(source), None);
+    }
+}
@@ -1,73 +1,594 @@
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use anyhow::{anyhow, bail};
-use tracing::info;
+use anyhow::{anyhow, bail, Context};
+use tracing::{info, warn};
 
-use crate::config::lef_matrix_config_dto::LedMatrixConfigDto;
-use crate::hw::port::Port;
+use crate::config::key_path::KeyPath;
+use crate::config::lef_matrix_config_dto::{LedMatrixConfigDto, RESERVED_TARGETS};
+use crate::config::matrix_dto::{MatrixDto, MatrixName};
+use crate::config::port_dto::PortDto;
+use crate::config::widget_dto::WidgetDto;
+use crate::gfx::transition::Transition;
+use crate::hw::device_kind::DeviceKind;
+use crate::hw::port::{Port, PortSettings};
+use crate::hw::splash::SplashAction;
+use crate::hw::terminal::{Terminal, TerminalTransport};
+use crate::hw::transport::{SerialTransport, Transport};
+use crate::hw::worker::PortWorker;
+use crate::state::StateStore;
+use crate::widget::scheduler::{RenderHolds, Schedule, Screen};
+use crate::widget::WidgetHandle;
 
 #[derive(Debug)]
 pub struct LedMatrixConfig {
-    pub left_port: Option<Arc<Mutex<Port>>>,
-    pub right_port: Option<Arc<Mutex<Port>>>,
+    /// In display order
+    pub matrices: Vec<Arc<PortWorker>>,
+    pub groups: HashMap<String, Vec<MatrixName>>,
 
     pub listen_address: Option<Arc<SocketAddr>>,
     pub unix_socket: Option<Arc<String>>,
     pub max_queue_size: usize,
     pub num_http_workers: usize,
+
+    pub widgets: Vec<Arc<WidgetHandle>>,
+    pub schedules: Vec<Arc<Schedule>>,
+    pub holds: RenderHolds,
+    pub transition: Transition,
+    pub state: Option<Arc<StateStore>>,
+    pub startup_splash: Option<SplashAction>,
+    pub startup_splash_duration: Duration,
+    pub shutdown_splash: Option<SplashAction>,
+    pub shutdown_timeout: Duration,
+    pub flush_queue_on_shutdown: bool,
+
+    pub simulate: bool,
+    pub terminal: Arc<Terminal>,
+    pub sources: ConfigSources,
+}
+
+/// The running configuration, replaced as a whole when the configuration file is reloaded.
+/// Users take a snapshot with `current` and keep working with it.
+#[derive(Debug)]
+pub struct SharedConfig {
+    current: RwLock<Arc<LedMatrixConfig>>,
+}
+
+impl SharedConfig {
+    pub fn new(config: Arc<LedMatrixConfig>) -> Self {
+        SharedConfig {
+            current: RwLock::new(config),
+        }
+    }
+
+    pub fn current(&self) -> Arc<LedMatrixConfig> {
+        self.current
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    pub fn replace(&self, config: Arc<LedMatrixConfig>) {
+        *self.current.write().unwrap_or_else(|err| err.into_inner()) = config;
+    }
+}
+
+/// The settings the running ports and widgets were built from, so that a reload can tell
+/// which of them changed.
+#[derive(Debug, Default)]
+pub struct ConfigSources {
+    ports: HashMap<MatrixName, PortDto>,
+    widgets: HashMap<String, WidgetDto>,
 }
 
 impl LedMatrixConfig {
-    fn log_port_version(&self, position: &str, port: Arc<Mutex<Port>>) -> anyhow::Result<()> {
-        let mut port = port
-            .lock()
-            .map_err(|err| anyhow!("Poisoned mutex: {err:?}"))?;
-        let version = port.get_device_version()?;
-        info!(%version, "{position} led matrix");
-        Ok(())
+    pub fn port(&self, name: &str) -> Option<&PortWorker> {
+        self.matrices
+            .iter()
+            .find(|port| port.name().as_str() == name)
+            .map(Arc::as_ref)
     }
-    pub fn log_led_matrix_versions(&self) -> anyhow::Result<()> {
-        if let Some(port) = self.left_port.as_ref() {
-            self.log_port_version("Left", port.clone())?;
+
+    /// The matrices a request targets, in order. A target is a matrix, a group, `all`, or a
+    /// comma-separated list of them; a matrix named more than once is only kept where it
+    /// first appears.
+    pub fn resolve(&self, target: &str) -> anyhow::Result<Vec<MatrixName>> {
+        let mut names = vec![];
+        for part in target.split(',').map(str::trim) {
+            if RESERVED_TARGETS.contains(&part) {
+                names.extend(self.matrices.iter().map(|port| port.name().clone()));
+            } else if let Some(members) = self.groups.get(part) {
+                names.extend(members.iter().cloned());
+            } else if let Some(port) = self.port(part) {
+                names.push(port.name().clone());
+            } else {
+                bail!("Matrix or group {part} is not configured");
+            }
         }
+        let mut seen = HashSet::new();
+        names.retain(|name| seen.insert(name.clone()));
+        if names.is_empty() {
+            bail!("No matrices are configured");
+        }
+        Ok(names)
+    }
 
-        if let Some(port) = self.right_port.as_ref() {
-            self.log_port_version("Right", port.clone())?;
+    pub fn widget(&self, name: &str) -> Option<&Arc<WidgetHandle>> {
+        self.widgets.iter().find(|widget| widget.name == name)
+    }
+
+    pub async fn log_led_matrix_versions(&self) -> anyhow::Result<()> {
+        for port in self.matrices.iter() {
+            let version = port.get_device_version().await?;
+            info!(%version, "{} led matrix", port.name());
         }
 
         Ok(())
     }
-}
 
-impl TryFrom<LedMatrixConfigDto> for LedMatrixConfig {
-    type Error = anyhow::Error;
+    /// Shows the action on every configured port; a failing port does not affect the others.
+    /// Splash frames and patterns are made for LED matrices, other modules are only put to
+    /// sleep.
+    pub async fn show_splash(&self, action: &SplashAction) {
+        for port in self.matrices.iter() {
+            if port.kind() != DeviceKind::LedMatrix && !matches!(action, SplashAction::Sleep) {
+                continue;
+            }
+            if let Err(err) = action.show(port).await {
+                warn!(?err, matrix = %port.name(), "Failed to show the splash");
+            }
+        }
+    }
 
-    fn try_from(value: LedMatrixConfigDto) -> Result<Self, Self::Error> {
-        if value.listen_address.is_none() && value.unix_socket.is_none() {
-            bail!("Either listen_address or unix_socket must be set");
+    /// Waits for the frames already queued on the ports and closes them.
+    pub async fn close_ports(&self) {
+        for port in self.matrices.iter() {
+            if let Err(err) = port.close().await {
+                warn!(?err, matrix = %port.name(), "Failed to close the port");
+            }
         }
+    }
 
-        let left_port = if let Some(left_port) = value.left_port {
-            Arc::new(Mutex::new(Port::try_from(left_port)?)).into()
-        } else {
-            None
-        };
+    /// Wakes up the panels a previous shutdown may have put to sleep, then shows the startup
+    /// splash.
+    pub async fn show_startup_splash(&self) {
+        if matches!(self.shutdown_splash, Some(SplashAction::Sleep)) {
+            for port in self.matrices.iter() {
+                if let Err(err) = port.set_sleeping(false).await {
+                    warn!(?err, "Failed to wake up the port");
+                }
+            }
+        }
+        if let Some(startup) = self.startup_splash.as_ref() {
+            self.show_splash(startup).await;
+            tokio::time::sleep(self.startup_splash_duration).await;
+        }
+    }
 
-        let right_port = if let Some(right_port) = value.right_port {
-            Arc::new(Mutex::new(Port::try_from(right_port)?)).into()
-        } else {
-            None
-        };
+    /// Builds the configuration to switch to from a changed configuration file. Ports and
+    /// widgets with unchanged settings are carried over as they are. Settings that only
+    /// take effect on a restart keep their running values.
+    pub fn reload(&self, mut value: LedMatrixConfigDto) -> anyhow::Result<LedMatrixConfig> {
+        let mut needs_restart = vec![];
+        if value.listen_address.map(Arc::new) != self.listen_address {
+            needs_restart.push("listen_address");
+        }
+        if value.unix_socket.as_ref() != self.unix_socket.as_deref() {
+            needs_restart.push("unix_socket");
+        }
+        if value.num_http_workers != self.num_http_workers {
+            needs_restart.push("num_http_workers");
+        }
+        if value.max_queue_size != self.max_queue_size {
+            needs_restart.push("max_queue_size");
+        }
+        if value.simulate != self.simulate {
+            needs_restart.push("simulate");
+        }
+        if !needs_restart.is_empty() {
+            warn!(?needs_restart, "Some settings only change on a restart");
+        }
+
+        value.listen_address = self.listen_address.as_deref().copied();
+        value.unix_socket = self.unix_socket.as_deref().cloned();
+        value.num_http_workers = self.num_http_workers;
+        value.max_queue_size = self.max_queue_size;
+        value.simulate = self.simulate;
+
+        Self::build(value, Some(self))
+    }
+
+    /// Ports of this configuration that `next` does not use anymore.
+    pub fn replaced_ports(&self, next: &LedMatrixConfig) -> Vec<Arc<PortWorker>> {
+        self.matrices
+            .iter()
+            .filter(|port| !next.matrices.iter().any(|next| Arc::ptr_eq(port, next)))
+            .cloned()
+            .collect()
+    }
+
+    /// Checks the configuration like building it does, without starting any ports.
+    pub fn check(value: LedMatrixConfigDto) -> anyhow::Result<()> {
+        Self::validate(value, None).map(|_| ())
+    }
+
+    fn build(
+        value: LedMatrixConfigDto,
+        previous: Option<&LedMatrixConfig>,
+    ) -> anyhow::Result<LedMatrixConfig> {
+        let (config, ports) = Self::validate(value, previous)?;
+        Ok(config.start(ports))
+    }
+
+    /// Does everything that can fail, so a rejected configuration has not started any ports.
+    /// The returned configuration has no matrices yet; they are started by `start`.
+    fn validate(
+        value: LedMatrixConfigDto,
+        previous: Option<&LedMatrixConfig>,
+    ) -> anyhow::Result<(LedMatrixConfig, Vec<(MatrixName, PlannedPort)>)> {
+        value.validate()?;
+
+        let mut sources = ConfigSources::default();
+        let terminal = previous
+            .map(|previous| previous.terminal.clone())
+            .unwrap_or_default();
+        let mut kinds = HashMap::new();
+        let mut ports = vec![];
+        for (
+            key,
+            MatrixDto {
+                name,
+                port: port_dto,
+                ..
+            },
+        ) in value.keyed_matrices()
+        {
+            let unchanged = previous.and_then(|previous| {
+                previous
+                    .matrices
+                    .iter()
+                    .find(|port| port.name() == &name)
+                    .filter(|_| previous.sources.ports.get(&name) == Some(&port_dto))
+            });
+
+            let port = match unchanged {
+                Some(worker) => PlannedPort::Running(worker.clone()),
+                None => PlannedPort::New(
+                    port_dto.clone(),
+                    PortSettings::try_from(&port_dto)
+                        .with_context(|| format!("Matrix {name}"))
+                        .context(key)?,
+                ),
+            };
+            kinds.insert(name.clone(), port_dto.kind);
+            sources.ports.insert(name.clone(), port_dto);
+            ports.push((name, port));
+        }
 
-        Ok(LedMatrixConfig {
-            left_port,
-            right_port,
+        let mut config = LedMatrixConfig {
+            matrices: vec![],
+            groups: value.groups.into_iter().collect(),
             listen_address: value.listen_address.map(Arc::new),
             unix_socket: value.unix_socket.map(Arc::new),
 
             max_queue_size: value.max_queue_size,
             num_http_workers: value.num_http_workers,
-        })
+            widgets: vec![],
+            schedules: vec![],
+            holds: match previous {
+                Some(previous) => previous.holds.carry_over(value.schedule.override_duration),
+                None => RenderHolds::new(value.schedule.override_duration),
+            },
+            transition: value.transition,
+            state: value
+                .state
+                .map(StateStore::try_from)
+                .transpose()
+                .context(KeyPath::new("state"))?
+                .map(Arc::new),
+            startup_splash: value
+                .splash
+                .startup
+                .map(SplashAction::try_from)
+                .transpose()
+                .context(KeyPath::new("splash").key("startup"))?,
+            startup_splash_duration: value.splash.startup_duration,
+            shutdown_splash: value
+                .splash
+                .shutdown
+                .map(SplashAction::try_from)
+                .transpose()
+                .context(KeyPath::new("splash").key("shutdown"))?,
+            shutdown_timeout: value.shutdown.timeout,
+            flush_queue_on_shutdown: value.shutdown.flush_queue,
+            simulate: value.simulate,
+            terminal,
+            sources: ConfigSources::default(),
+        };
+
+        for (index, widget_dto) in value.widgets.into_iter().enumerate() {
+            let widget = (|| {
+                if config.widget(&widget_dto.name).is_some() {
+                    bail!("Duplicate widget name: {}", widget_dto.name);
+                }
+                let unchanged = previous
+                    .filter(|previous| {
+                        previous.sources.widgets.get(&widget_dto.name) == Some(&widget_dto)
+                    })
+                    .and_then(|previous| previous.widget(&widget_dto.name));
+                let widget = match unchanged {
+                    Some(widget) => widget.clone(),
+                    None => Arc::new(WidgetHandle::try_from(widget_dto.clone())?),
+                };
+                if let Some(matrix) = widget.matrix.as_ref() {
+                    match kinds.get(matrix) {
+                        None => bail!(
+                            "Matrix {matrix} for widget {} is not configured",
+                            widget.name
+                        ),
+                        Some(kind) if *kind != DeviceKind::LedMatrix => bail!(
+                            "Widget {} renders 9x34 frames, {matrix} is a {kind} port",
+                            widget.name
+                        ),
+                        Some(_) => {}
+                    }
+                }
+                Ok(widget)
+            })()
+            .with_context(|| KeyPath::new("widgets").index(index))?;
+            sources.widgets.insert(widget_dto.name.clone(), widget_dto);
+            config.widgets.push(widget);
+        }
+
+        let schedule = value.schedule;
+        let legacy = [
+            (
+                KeyPath::new("schedule").key("left"),
+                MatrixName::left(),
+                schedule.left,
+            ),
+            (
+                KeyPath::new("schedule").key("right"),
+                MatrixName::right(),
+                schedule.right,
+            ),
+        ];
+        let configured = schedule.matrices.into_iter().map(|(matrix, screens)| {
+            let key = KeyPath::new("schedule")
+                .key("matrices")
+                .key(matrix.as_str());
+            (key, matrix, screens)
+        });
+        for (key, matrix, screens) in legacy.into_iter().chain(configured) {
+            if screens.is_empty() {
+                continue;
+            }
+            let screens = (|| {
+                match kinds.get(&matrix) {
+                    None => bail!("Matrix {matrix} for the schedule is not configured"),
+                    Some(kind) if *kind != DeviceKind::LedMatrix => bail!(
+                        "Widgets render 9x34 frames, {matrix} is a {kind} port and cannot have a schedule"
+                    ),
+                    Some(_) => {}
+                }
+                if config.schedules.iter().any(|other| other.matrix == matrix) {
+                    bail!("Matrix {matrix} has more than one schedule");
+                }
+
+                screens
+                    .into_iter()
+                    .map(|screen| {
+                        let widget = config
+                            .widget(&screen.widget)
+                            .ok_or_else(|| {
+                                anyhow!("Scheduled widget {} does not exist", screen.widget)
+                            })?
+                            .clone();
+                        if widget.matrix.is_some() {
+                            bail!(
+                                "Scheduled widget {} must not have a port, the schedule drives it",
+                                widget.name
+                            );
+                        }
+                        Screen::try_from_dto(screen, widget)
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })()
+            .context(key)?;
+
+            config.schedules.push(Arc::new(Schedule {
+                matrix,
+                screens,
+                sysfs_root: schedule.sysfs_root.clone(),
+            }));
+        }
+
+        for (index, widget) in config
+            .widgets
+            .iter()
+            .enumerate()
+            .filter(|(_, widget)| widget.matrix.is_none())
+        {
+            let scheduled = config
+                .schedules
+                .iter()
+                .flat_map(|schedule| schedule.screens.iter())
+                .any(|screen| Arc::ptr_eq(screen.widget(), widget));
+            if !scheduled {
+                return Err(anyhow!(
+                    "Widget {} has no port and no schedule shows it",
+                    widget.name
+                ))
+                .context(KeyPath::new("widgets").index(index));
+            }
+        }
+
+        config.sources = sources;
+        Ok((config, ports))
+    }
+
+    /// Starts the new ports of a validated configuration; nothing can fail anymore.
+    fn start(mut self, ports: Vec<(MatrixName, PlannedPort)>) -> Self {
+        self.matrices = ports
+            .into_iter()
+            .map(|(name, port)| match port {
+                PlannedPort::Running(worker) => worker,
+                PlannedPort::New(port_dto, settings) => {
+                    let transport: Box<dyn Transport> = if self.simulate {
+                        Box::new(TerminalTransport::new(name.clone(), self.terminal.clone()))
+                    } else {
+                        Box::new(SerialTransport::from(&port_dto))
+                    };
+                    let port = Port::new(transport, settings);
+                    Arc::new(PortWorker::spawn(name, port, self.max_queue_size))
+                }
+            })
+            .collect();
+        let names: Vec<MatrixName> = self
+            .matrices
+            .iter()
+            .map(|port| port.name().clone())
+            .collect();
+        self.terminal.retain(&names);
+        self
+    }
+}
+
+/// A port of a validated configuration
+enum PlannedPort {
+    /// Carried over from the previous configuration
+    Running(Arc<PortWorker>),
+    /// To be started
+    New(PortDto, PortSettings),
+}
+
+impl TryFrom<LedMatrixConfigDto> for LedMatrixConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(value: LedMatrixConfigDto) -> Result<Self, Self::Error> {
+        Self::build(value, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_dto(interval: &str, max_queue_size: usize) -> LedMatrixConfigDto {
+        toml::from_str(&format!(
+            r#"
+            listen_address = "127.0.0.1:45935"
+            max_queue_size = {max_queue_size}
+            simulate = true
+
+            [left_port]
+            path = "/dev/ttyACM0"
+
+            [right_port]
+            path = "/dev/ttyACM1"
+
+            [[matrix]]
+            name = "desk"
+            path = "/dev/ttyACM2"
+
+            [groups]
+            laptop = ["right", "left"]
+
+            [[widgets]]
+            name = "clock"
+            kind = "clock"
+            port = "left"
+            interval = "{interval}"
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reload() -> anyhow::Result<()> {
+        let current = LedMatrixConfig::try_from(config_dto("1s", 10))?;
+        current.holds.hold(&MatrixName::left());
+
+        let mut dto = config_dto("2s", 20);
+        dto.right_port = None;
+        dto.groups.clear();
+        let next = current.reload(dto)?;
+
+        assert!(Arc::ptr_eq(&current.matrices[0], &next.matrices[0]));
+        assert!(next.port("right").is_none());
+        assert_eq!(
+            next.terminal.names(),
+            [MatrixName::left(), MatrixName::from("desk")]
+        );
+        assert_eq!(current.replaced_ports(&next).len(), 1);
+        assert!(!Arc::ptr_eq(&current.widgets[0], &next.widgets[0]));
+        assert_eq!(next.max_queue_size, 10);
+        assert!(next.holds.is_held(&MatrixName::left()));
+
+        let unchanged = next.reload(config_dto("2s", 10))?;
+        assert!(Arc::ptr_eq(&next.widgets[0], &unchanged.widgets[0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zero_widget_interval() {
+        let err = LedMatrixConfig::check(config_dto("0s", 10)).unwrap_err();
+        assert!(err.to_string().contains("interval above zero"), "{err}");
+    }
+
+    #[test]
+    fn test_unscheduled_widget() {
+        let mut dto = config_dto("1s", 10);
+        dto.widgets[0].port = None;
+        let err = LedMatrixConfig::check(dto).unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&KeyPath::new("widgets").index(0)));
+        assert!(
+            format!("{err:#}").contains("no schedule shows it"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn test_failed_reload_starts_no_ports() -> anyhow::Result<()> {
+        let current = LedMatrixConfig::try_from(config_dto("1s", 10))?;
+        assert_eq!(current.terminal.names().len(), 3);
+
+        // A new matrix, then a duplicate widget
+        let invalid = || {
+            let mut dto = config_dto("1s", 10);
+            dto.matrices.push(MatrixDto::new(
+                MatrixName::from("shelf"),
+                PortDto::new("/dev/ttyACM3".to_string()),
+            ));
+            dto.widgets.push(dto.widgets[0].clone());
+            dto
+        };
+        assert!(current.reload(invalid()).is_err());
+        assert!(LedMatrixConfig::check(invalid()).is_err());
+        assert_eq!(current.terminal.names().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve() -> anyhow::Result<()> {
+        let config = LedMatrixConfig::try_from(config_dto("1s", 10))?;
+        let resolve = |target| -> anyhow::Result<Vec<String>> {
+            Ok(config
+                .resolve(target)?
+                .iter()
+                .map(ToString::to_string)
+                .collect())
+        };
+
+        assert_eq!(resolve("desk")?, ["desk"]);
+        assert_eq!(resolve("laptop")?, ["right", "left"]);
+        assert_eq!(resolve("all")?, ["left", "right", "desk"]);
+        assert_eq!(resolve("desk, laptop")?, ["desk", "right", "left"]);
+        assert_eq!(resolve("left,laptop")?, ["left", "right"]);
+        assert_eq!(resolve("all,desk")?, ["left", "right", "desk"]);
+        assert!(resolve("nope").is_err());
+        Ok(())
     }
 }
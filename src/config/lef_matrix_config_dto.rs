@@ -1,15 +1,37 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::net::SocketAddr;
 use std::path::Path;
 
+use anyhow::{bail, Context};
 use serde::{Deserialize, Serialize};
 
+use crate::config::key_path::KeyPath;
+use crate::config::matrix_dto::{MatrixDto, MatrixName};
 use crate::config::port_dto::PortDto;
+use crate::config::schedule_dto::ScheduleDto;
+use crate::config::shutdown_dto::ShutdownDto;
+use crate::config::splash_dto::SplashDto;
+use crate::config::state_dto::StateDto;
+use crate::config::widget_dto::WidgetDto;
+use crate::gfx::transition::Transition;
+use crate::hw::device_kind::DeviceKind;
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct LedMatrixConfigDto {
+    /// The matrix `left`
     pub left_port: Option<PortDto>,
+    /// The matrix `right`
     pub right_port: Option<PortDto>,
 
+    /// Further matrices, ordered after `left` and `right`.
+    #[serde(default, rename = "matrix")]
+    pub matrices: Vec<MatrixDto>,
+
+    /// Named ordered sets of matrices that requests can target and span.
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<MatrixName>>,
+
     pub listen_address: Option<SocketAddr>,
     pub unix_socket: Option<String>,
 
@@ -18,15 +40,260 @@ pub struct LedMatrixConfigDto {
     // default 1
     #[serde(default = "super::default_http_workers")]
     pub num_http_workers: usize,
+
+    #[serde(default)]
+    pub widgets: Vec<WidgetDto>,
+
+    #[serde(default)]
+    pub schedule: ScheduleDto,
+
+    /// The transition for explicit render requests and schedule screen switches.
+    #[serde(default)]
+    pub transition: Transition,
+
+    /// Shows the frames in the terminal instead of writing them to the serial ports.
+    #[serde(default)]
+    pub simulate: bool,
+
+    #[serde(default)]
+    pub splash: SplashDto,
+
+    #[serde(default)]
+    pub shutdown: ShutdownDto,
+
+    /// Persists the displayed frames and widget states to restore them after a restart.
+    #[serde(default)]
+    pub state: Option<StateDto>,
+}
+
+/// Targets that address all matrices and cannot name a matrix or a group
+pub const RESERVED_TARGETS: [&str; 2] = ["all", "span"];
+
+/// Something wrong with a setting, and where it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub key: KeyPath,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(key: KeyPath, message: impl Into<String>) -> Self {
+        Problem {
+            key,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+impl LedMatrixConfigDto {
+    /// All matrices in order, `left_port` and `right_port` first, with the key that
+    /// configures each of them.
+    pub fn keyed_matrices(&self) -> Vec<(KeyPath, MatrixDto)> {
+        let legacy = [
+            ("left_port", MatrixName::left(), &self.left_port),
+            ("right_port", MatrixName::right(), &self.right_port),
+        ];
+        legacy
+            .into_iter()
+            .filter_map(|(key, name, port)| {
+                let port = port.clone()?;
+                Some((KeyPath::new(key), MatrixDto::new(name, port)))
+            })
+            .chain(
+                self.matrices
+                    .iter()
+                    .enumerate()
+                    .map(|(index, matrix)| (KeyPath::new("matrix").index(index), matrix.clone())),
+            )
+            .collect()
+    }
+
+    /// Everything wrong with the settings themselves, without looking at the system.
+    pub fn problems(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+        if self.listen_address.is_none() && self.unix_socket.is_none() {
+            problems.push(Problem::new(
+                KeyPath::new("listen_address"),
+                "Either listen_address or unix_socket must be set",
+            ));
+        }
+
+        let mut names = HashSet::new();
+        let mut paths = HashMap::new();
+        let valid = |name: &str| {
+            let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+            !name.is_empty() && name.chars().all(valid)
+        };
+        for (key, matrix) in self.keyed_matrices() {
+            let name = matrix.name.as_str();
+            if !valid(name) {
+                problems.push(Problem::new(
+                    key.clone().key("name"),
+                    format!("Matrix name {name:?} must only contain letters, digits, - and _"),
+                ));
+            }
+            if RESERVED_TARGETS.contains(&name) {
+                problems.push(Problem::new(
+                    key.clone().key("name"),
+                    format!("Matrix name {name} is reserved"),
+                ));
+            }
+            if !names.insert(matrix.name.clone()) {
+                problems.push(Problem::new(
+                    key.clone().key("name"),
+                    format!("Matrix {name} is configured more than once"),
+                ));
+            }
+            if self.simulate && matrix.port.kind != DeviceKind::LedMatrix {
+                problems.push(Problem::new(
+                    key.clone().key("kind"),
+                    format!(
+                        "Matrix {name} is a {} port, which cannot be simulated",
+                        matrix.port.kind
+                    ),
+                ));
+            }
+            if let Some(other) = paths.insert(matrix.port.path.clone(), matrix.name.clone()) {
+                problems.push(Problem::new(
+                    key.clone().key("path"),
+                    format!("Matrices {other} and {name} both use {}", matrix.port.path),
+                ));
+            }
+        }
+
+        for (group, members) in &self.groups {
+            let key = KeyPath::new("groups").key(group);
+            if !valid(group) {
+                problems.push(Problem::new(
+                    key.clone(),
+                    format!("Group name {group:?} must only contain letters, digits, - and _"),
+                ));
+            }
+            if names.contains(group.as_str()) || RESERVED_TARGETS.contains(&group.as_str()) {
+                problems.push(Problem::new(
+                    key.clone(),
+                    format!("Group name {group} is taken"),
+                ));
+            }
+            if members.is_empty() {
+                problems.push(Problem::new(key.clone(), format!("Group {group} is empty")));
+            }
+            for member in members.iter().filter(|member| !names.contains(*member)) {
+                problems.push(Problem::new(
+                    key.clone(),
+                    format!("Matrix {member} of group {group} is not configured"),
+                ));
+            }
+        }
+
+        for (index, widget) in self.widgets.iter().enumerate() {
+            if widget.interval.is_zero() {
+                problems.push(Problem::new(
+                    KeyPath::new("widgets").index(index).key("interval"),
+                    format!("Widget {} needs an interval above zero", widget.name),
+                ));
+            }
+        }
+        let schedule = &self.schedule;
+        let screens = [("left", &schedule.left), ("right", &schedule.right)]
+            .into_iter()
+            .map(|(matrix, screens)| (KeyPath::new("schedule").key(matrix), screens))
+            .chain(schedule.matrices.iter().map(|(matrix, screens)| {
+                let key = KeyPath::new("schedule")
+                    .key("matrices")
+                    .key(matrix.as_str());
+                (key, screens)
+            }));
+        for (key, screens) in screens {
+            for (index, screen) in screens.iter().enumerate() {
+                if screen.duration.is_zero() {
+                    problems.push(Problem::new(
+                        key.clone().index(index).key("duration"),
+                        format!("Screen {} needs a duration above zero", screen.widget),
+                    ));
+                }
+            }
+        }
+        if let Some(state) = self.state.as_ref() {
+            if state.save_interval.is_zero() {
+                problems.push(Problem::new(
+                    KeyPath::new("state").key("save_interval"),
+                    "State save_interval must be above zero",
+                ));
+            }
+        }
+
+        if let Err(err) = self.transition.validate() {
+            problems.push(Problem::new(KeyPath::new("transition"), err.to_string()));
+        }
+        if self.max_queue_size == 0 {
+            problems.push(Problem::new(
+                KeyPath::new("max_queue_size"),
+                "max_queue_size must be at least 1",
+            ));
+        }
+        if self.num_http_workers == 0 {
+            problems.push(Problem::new(
+                KeyPath::new("num_http_workers"),
+                "num_http_workers must be at least 1",
+            ));
+        }
+        problems
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let problems = self.problems();
+        if !problems.is_empty() {
+            let problems: Vec<String> = problems.iter().map(Problem::to_string).collect();
+            bail!("{}", problems.join("; "));
+        }
+        Ok(())
+    }
+
+    /// Parses the configuration, rejecting unknown keys such as misspelled settings.
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut unknown = vec![];
+        let config: LedMatrixConfigDto =
+            serde_ignored::deserialize(toml::Deserializer::new(content), |path| {
+                unknown.push(KeyPath::from(&path));
+            })?;
+        for (index, matrix) in config.matrices.iter().enumerate() {
+            unknown.extend(
+                matrix
+                    .unknown
+                    .keys()
+                    .map(|key| KeyPath::new("matrix").index(index).key(key)),
+            );
+        }
+
+        if !unknown.is_empty() {
+            let keys: Vec<String> = unknown
+                .iter()
+                .map(|key| match key.line(content) {
+                    Some(line) => format!("{key} (line {line})"),
+                    None => key.to_string(),
+                })
+                .collect();
+            bail!("Unknown keys: {}", keys.join(", "));
+        }
+        Ok(config)
+    }
 }
 
 impl TryFrom<&Path> for LedMatrixConfigDto {
     type Error = anyhow::Error;
 
     fn try_from(value: &Path) -> Result<Self, Self::Error> {
-        let content = std::fs::read_to_string(value)?;
-        let config: LedMatrixConfigDto = toml::from_str(&content)?;
-        Ok(config)
+        let content = std::fs::read_to_string(value)
+            .with_context(|| format!("Failed to read {}", value.display()))?;
+        Self::parse(&content)
+            .with_context(|| format!("Invalid configuration in {}", value.display()))
     }
 }
 
@@ -35,30 +302,109 @@ mod tests {
     use std::time::Duration;
 
     use super::*;
+    use crate::config::schedule_dto::{PowerSource, ScreenDto};
+    use crate::config::splash_dto::SplashActionDto;
+    use crate::config::widget_dto::{ClockWidgetDto, HourFormat, WidgetKindDto};
+    use crate::gfx::transition::TransitionKind;
 
     #[test]
     fn test() -> anyhow::Result<()> {
         let config = LedMatrixConfigDto {
             left_port: PortDto {
                 path: "/dev/ttyACM0".to_string(),
+                kind: DeviceKind::LedMatrix,
                 baud_rate: 115200,
                 timeout: Duration::from_secs(2),
                 wait_delay: None,
                 keep_open: false,
+                rotation: 0,
+                flip_horizontal: false,
+                flip_vertical: false,
+                full_refresh_every: None,
+                color: None,
             }
             .into(),
             right_port: PortDto {
                 path: "/dev/ttyACM1".to_string(),
+                kind: DeviceKind::LedMatrix,
                 baud_rate: 115200,
                 timeout: Duration::from_secs(2),
                 wait_delay: None,
                 keep_open: false,
+                rotation: 180,
+                flip_horizontal: true,
+                flip_vertical: false,
+                full_refresh_every: Some(100),
+                color: None,
             }
             .into(),
+            matrices: vec![MatrixDto::new(
+                MatrixName::from("desk"),
+                PortDto {
+                    kind: DeviceKind::C1Minimal,
+                    color: Some([255, 128, 0]),
+                    ..PortDto::new("/dev/ttyACM2".to_string())
+                },
+            )],
+            groups: BTreeMap::from([(
+                "laptop".to_string(),
+                vec![MatrixName::left(), MatrixName::right()],
+            )]),
             listen_address: SocketAddr::from(([127, 0, 0, 1], 45935)).into(),
             unix_socket: "/tmp/led-matrix.sock".to_string().into(),
             max_queue_size: 10,
             num_http_workers: 1,
+            widgets: vec![WidgetDto {
+                name: "clock".to_string(),
+                port: None,
+                enabled: true,
+                interval: Duration::from_millis(500),
+                kind: WidgetKindDto::Clock(ClockWidgetDto {
+                    show_seconds: true,
+                    hour_format: HourFormat::H12,
+                    blink_colon: true,
+                    timezone: Some("Europe/Berlin".to_string()),
+                }),
+            }],
+            schedule: ScheduleDto {
+                override_duration: Duration::from_secs(5),
+                sysfs_root: "/sys".into(),
+                left: vec![ScreenDto {
+                    widget: "clock".to_string(),
+                    duration: Duration::from_secs(10),
+                    hours: Some("08:00-18:00".to_string()),
+                    power: Some(PowerSource::Battery),
+                }],
+                right: vec![],
+                matrices: BTreeMap::from([(
+                    MatrixName::from("desk"),
+                    vec![ScreenDto {
+                        widget: "clock".to_string(),
+                        duration: Duration::from_secs(30),
+                        hours: None,
+                        power: None,
+                    }],
+                )]),
+            },
+            transition: Transition {
+                kind: TransitionKind::Crossfade,
+                duration: Duration::from_millis(250),
+                fps: 20,
+            },
+            simulate: false,
+            splash: SplashDto {
+                startup: Some(SplashActionDto::Text("HI".to_string())),
+                startup_duration: Duration::from_secs(1),
+                shutdown: Some(SplashActionDto::Sleep),
+            },
+            shutdown: ShutdownDto {
+                timeout: Duration::from_secs(3),
+                flush_queue: true,
+            },
+            state: Some(StateDto {
+                dir: "/var/lib/led_matrix".into(),
+                save_interval: Duration::from_secs(30),
+            }),
         };
 
         let repr = toml::to_string(&config)?;
@@ -67,4 +413,101 @@ mod tests {
         assert_eq!(config, parsed);
         Ok(())
     }
+
+    #[test]
+    fn test_problems() -> anyhow::Result<()> {
+        let config: LedMatrixConfigDto = toml::from_str(
+            r#"
+            max_queue_size = 0
+            simulate = true
+
+            [left_port]
+            path = "/dev/ttyACM0"
+
+            [right_port]
+            path = "/dev/ttyACM0"
+
+            [[matrix]]
+            name = "left"
+            path = "/dev/ttyACM1"
+
+            [[matrix]]
+            name = "display"
+            path = "/dev/ttyACM2"
+            kind = "b1_display"
+
+            [groups]
+            all = ["left"]
+            desk = ["right", "desk"]
+            "a,b" = ["left"]
+
+            [transition]
+            kind = "crossfade"
+            fps = 120
+
+            [[widgets]]
+            name = "cpu"
+            kind = "cpu"
+            interval = "0s"
+
+            [[schedule.left]]
+            widget = "cpu"
+            duration = "0s"
+
+            [state]
+            save_interval = "0s"
+            "#,
+        )?;
+        let problems: Vec<String> = config.problems().iter().map(Problem::to_string).collect();
+        assert_eq!(
+            problems,
+            [
+                "listen_address: Either listen_address or unix_socket must be set",
+                "right_port.path: Matrices left and right both use /dev/ttyACM0",
+                "matrix[0].name: Matrix left is configured more than once",
+                "matrix[1].kind: Matrix display is a b1_display port, which cannot be simulated",
+                "groups.\"a,b\": Group name \"a,b\" must only contain letters, digits, - and _",
+                "groups.all: Group name all is taken",
+                "groups.desk: Matrix desk of group desk is not configured",
+                "widgets[0].interval: Widget cpu needs an interval above zero",
+                "schedule.left[0].duration: Screen cpu needs a duration above zero",
+                "state.save_interval: State save_interval must be above zero",
+                "transition: Transition fps must be at most 60; got 120",
+                "max_queue_size: max_queue_size must be at least 1",
+            ]
+        );
+        assert!(config.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let err = LedMatrixConfigDto::parse(
+            r#"
+            listen_address = "127.0.0.1:45999"
+            max_queu_size = 3
+
+            [[matrix]]
+            name = "left"
+            path = "/dev/ttyACM0"
+            rotaton = "cw"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown keys: max_queu_size (line 3), matrix[0].rotaton (line 8)"
+        );
+
+        let err = LedMatrixConfigDto::parse(
+            r#"
+            [[widgets]]
+            name = "clock"
+            kind = "clock"
+            timezon = "UTC"
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown field `timezon`"), "{err}");
+    }
 }
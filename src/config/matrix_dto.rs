@@ -0,0 +1,68 @@
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::port_dto::PortDto;
+
+/// Names a matrix in requests, widgets and schedules. `left_port` and `right_port` configure
+/// the matrices `left` and `right`.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct MatrixName(Arc<str>);
+
+impl MatrixName {
+    pub fn left() -> Self {
+        MatrixName::from("left")
+    }
+
+    pub fn right() -> Self {
+        MatrixName::from("right")
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for MatrixName {
+    fn from(value: &str) -> Self {
+        MatrixName(value.into())
+    }
+}
+
+impl Borrow<str> for MatrixName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for MatrixName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MatrixDto {
+    pub name: MatrixName,
+
+    #[serde(flatten)]
+    pub port: PortDto,
+
+    /// Whatever `port` did not take, i.e. unknown keys, which `flatten` would drop silently
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, toml::Value>,
+}
+
+impl MatrixDto {
+    pub fn new(name: MatrixName, port: PortDto) -> Self {
+        MatrixDto {
+            name,
+            port,
+            unknown: BTreeMap::new(),
+        }
+    }
+}
@@ -1,8 +1,16 @@
+use std::path::PathBuf;
 use std::time::Duration;
 
+pub mod key_path;
 pub mod led_matrix_config;
 pub mod lef_matrix_config_dto;
+pub mod matrix_dto;
 pub mod port_dto;
+pub mod schedule_dto;
+pub mod shutdown_dto;
+pub mod splash_dto;
+pub mod state_dto;
+pub mod widget_dto;
 
 fn yes() -> bool {
     true
@@ -23,3 +31,67 @@ fn default_http_workers() -> usize {
 fn default_max_queue_size() -> usize {
     1
 }
+
+fn default_widget_interval() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_procfs_root() -> PathBuf {
+    PathBuf::from("/proc")
+}
+
+fn default_sysfs_root() -> PathBuf {
+    PathBuf::from("/sys")
+}
+
+fn default_battery_warning_threshold() -> u8 {
+    15
+}
+
+fn default_temperature_min() -> i32 {
+    30
+}
+
+fn default_temperature_max() -> i32 {
+    100
+}
+
+fn default_temperature_warning() -> i32 {
+    85
+}
+
+fn default_max_rpm() -> u32 {
+    6000
+}
+
+fn default_command_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_command_max_output() -> usize {
+    64 * 1024
+}
+
+fn default_command_max() -> f64 {
+    100.0
+}
+
+fn default_override_duration() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_splash_duration() -> Duration {
+    Duration::from_secs(2)
+}
+
+fn default_shutdown_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_state_dir() -> PathBuf {
+    PathBuf::from("/var/lib/led_matrix")
+}
+
+fn default_state_save_interval() -> Duration {
+    Duration::from_secs(10)
+}
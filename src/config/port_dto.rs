@@ -2,10 +2,15 @@ use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+use crate::hw::device_kind::DeviceKind;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct PortDto {
     pub path: String,
 
+    #[serde(default)]
+    pub kind: DeviceKind,
+
     #[serde(default = "super::default_baud_rate")]
     pub baud_rate: u32,
 
@@ -17,4 +22,55 @@ pub struct PortDto {
 
     #[serde(default = "super::yes")]
     pub keep_open: bool,
+
+    /// Clockwise rotation in degrees: 0, 90, 180 or 270. Ports rotated by 90 or 270 degrees
+    /// accept landscape 34x9 images.
+    #[serde(default)]
+    pub rotation: u16,
+
+    #[serde(default)]
+    pub flip_horizontal: bool,
+
+    #[serde(default)]
+    pub flip_vertical: bool,
+
+    /// Only changed columns are sent to the device; every N frames all columns are sent
+    /// anyway in case the device state drifted. Never forced if not set.
+    #[serde(default)]
+    pub full_refresh_every: Option<u32>,
+
+    /// Color of a `c1_minimal` module at full brightness, white if not set.
+    #[serde(default)]
+    pub color: Option<[u8; 3]>,
+}
+
+impl PortDto {
+    /// A port at `path` with the same defaults as a deserialized one.
+    pub fn new(path: String) -> Self {
+        PortDto {
+            path,
+            kind: DeviceKind::default(),
+            baud_rate: super::default_baud_rate(),
+            timeout: super::default_port_timeout(),
+            wait_delay: None,
+            keep_open: super::yes(),
+            rotation: 0,
+            flip_horizontal: false,
+            flip_vertical: false,
+            full_refresh_every: None,
+            color: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_matches_defaults() -> anyhow::Result<()> {
+        let port: PortDto = toml::from_str(r#"path = "/dev/ttyACM0""#)?;
+        assert_eq!(PortDto::new("/dev/ttyACM0".to_string()), port);
+        Ok(())
+    }
 }
@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::matrix_dto::MatrixName;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ScheduleDto {
+    /// How long an explicit render request suspends widget output on its port.
+    #[serde(with = "humantime_serde", default = "super::default_override_duration")]
+    pub override_duration: Duration,
+
+    /// Used to evaluate `power` conditions.
+    #[serde(default = "super::default_sysfs_root")]
+    pub sysfs_root: PathBuf,
+
+    #[serde(default)]
+    pub left: Vec<ScreenDto>,
+
+    #[serde(default)]
+    pub right: Vec<ScreenDto>,
+
+    /// Screens of the other matrices, by matrix name
+    #[serde(default)]
+    pub matrices: BTreeMap<MatrixName, Vec<ScreenDto>>,
+}
+
+impl Default for ScheduleDto {
+    fn default() -> Self {
+        ScheduleDto {
+            override_duration: super::default_override_duration(),
+            sysfs_root: super::default_sysfs_root(),
+            left: vec![],
+            right: vec![],
+            matrices: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerSource {
+    Ac,
+    Battery,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ScreenDto {
+    pub widget: String,
+
+    #[serde(with = "humantime_serde")]
+    pub duration: Duration,
+
+    /// Time range such as `08:00-18:00` in the system's local timezone; ranges may wrap past
+    /// midnight. The `timezone` of a clock widget does not apply.
+    #[serde(default)]
+    pub hours: Option<String>,
+
+    #[serde(default)]
+    pub power: Option<PowerSource>,
+}
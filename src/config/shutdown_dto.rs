@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ShutdownDto {
+    /// How long in-flight frames, the shutdown splash and closing the ports may take.
+    #[serde(with = "humantime_serde", default = "super::default_shutdown_timeout")]
+    pub timeout: Duration,
+
+    /// Renders the queued tasks before shutting down instead of dropping them.
+    #[serde(default)]
+    pub flush_queue: bool,
+}
+
+impl Default for ShutdownDto {
+    fn default() -> Self {
+        ShutdownDto {
+            timeout: super::default_shutdown_timeout(),
+            flush_queue: false,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::hw::Pattern;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SplashDto {
+    /// Shown on all ports once the daemon has started.
+    #[serde(default)]
+    pub startup: Option<SplashActionDto>,
+
+    /// How long the startup splash stays before restored frames and widgets replace it.
+    #[serde(with = "humantime_serde", default = "super::default_splash_duration")]
+    pub startup_duration: Duration,
+
+    /// Shown on all ports on a graceful shutdown.
+    #[serde(default)]
+    pub shutdown: Option<SplashActionDto>,
+}
+
+impl Default for SplashDto {
+    fn default() -> Self {
+        SplashDto {
+            startup: None,
+            startup_duration: super::default_splash_duration(),
+            shutdown: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SplashActionDto {
+    /// A 9x34 image file
+    Image(PathBuf),
+    /// A built-in firmware pattern
+    Pattern(Pattern),
+    /// Up to ten characters of the built-in font
+    Text(String),
+    Blank,
+    /// Puts the panels to sleep; they are woken up on the next start
+    Sleep,
+}
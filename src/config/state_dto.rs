@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct StateDto {
+    /// Where the last frames and widget states are kept between restarts.
+    #[serde(default = "super::default_state_dir")]
+    pub dir: PathBuf,
+
+    /// How often changes are written to `dir`.
+    #[serde(
+        with = "humantime_serde",
+        default = "super::default_state_save_interval"
+    )]
+    pub save_interval: Duration,
+}
@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::matrix_dto::MatrixName;
+use crate::gfx::graph::{GraphStyle, Scale};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WidgetDto {
+    pub name: String,
+
+    /// Widgets without a port are only shown through a schedule.
+    #[serde(default)]
+    pub port: Option<MatrixName>,
+
+    #[serde(default = "super::yes")]
+    pub enabled: bool,
+
+    #[serde(with = "humantime_serde", default = "super::default_widget_interval")]
+    pub interval: Duration,
+
+    #[serde(flatten)]
+    pub kind: WidgetKindDto,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WidgetKindDto {
+    Clock(ClockWidgetDto),
+    Cpu(CpuWidgetDto),
+    Memory(MemoryWidgetDto),
+    Load(LoadWidgetDto),
+    Battery(BatteryWidgetDto),
+    Network(NetworkWidgetDto),
+    Disk(DiskWidgetDto),
+    Temperature(TemperatureWidgetDto),
+    Command(CommandWidgetDto),
+}
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub enum HourFormat {
+    #[default]
+    #[serde(rename = "24h")]
+    H24,
+    #[serde(rename = "12h")]
+    H12,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClockWidgetDto {
+    #[serde(default)]
+    pub show_seconds: bool,
+
+    #[serde(default)]
+    pub hour_format: HourFormat,
+
+    #[serde(default = "super::yes")]
+    pub blink_colon: bool,
+
+    /// IANA timezone name, e.g. `Europe/Berlin`; the local timezone is used if not set.
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CpuWidgetDto {
+    #[serde(default = "super::default_procfs_root")]
+    pub procfs_root: PathBuf,
+
+    /// One bar or sparkline per core instead of the aggregate utilization.
+    #[serde(default)]
+    pub per_core: bool,
+
+    #[serde(default)]
+    pub style: GraphStyle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryWidgetDto {
+    #[serde(default = "super::default_procfs_root")]
+    pub procfs_root: PathBuf,
+
+    #[serde(default)]
+    pub style: GraphStyle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LoadWidgetDto {
+    #[serde(default = "super::default_procfs_root")]
+    pub procfs_root: PathBuf,
+
+    #[serde(default)]
+    pub style: GraphStyle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BatteryWidgetDto {
+    #[serde(default = "super::default_sysfs_root")]
+    pub sysfs_root: PathBuf,
+
+    /// Power supply name, e.g. `BAT1`; all batteries are averaged if not set.
+    #[serde(default)]
+    pub battery: Option<String>,
+
+    /// Blink when discharging at or below this percentage.
+    #[serde(default = "super::default_battery_warning_threshold")]
+    pub warning_threshold: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkWidgetDto {
+    #[serde(default = "super::default_procfs_root")]
+    pub procfs_root: PathBuf,
+
+    /// Interface name, e.g. `wlp1s0`; all interfaces except `lo` are summed if not set.
+    #[serde(default)]
+    pub interface: Option<String>,
+
+    #[serde(default)]
+    pub scale: Scale,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DiskWidgetDto {
+    #[serde(default = "super::default_procfs_root")]
+    pub procfs_root: PathBuf,
+
+    /// Used to tell partitions from disks.
+    #[serde(default = "super::default_sysfs_root")]
+    pub sysfs_root: PathBuf,
+
+    /// Block device name, e.g. `nvme0n1`; all disks are summed if not set, leaving out
+    /// partitions, `dm-*` and `md*` devices.
+    #[serde(default)]
+    pub device: Option<String>,
+
+    #[serde(default)]
+    pub scale: Scale,
+}
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TemperatureStyle {
+    /// One column per sensor filled from the bottom.
+    #[default]
+    Thermometer,
+    /// One number per sensor, stacked top to bottom.
+    Digits,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TemperatureWidgetDto {
+    #[serde(default = "super::default_sysfs_root")]
+    pub sysfs_root: PathBuf,
+
+    /// Sensor labels such as `Tctl`, `k10temp/Tctl`, `fan1` or `acpitz/thermal_zone0`;
+    /// the first temperature sensor is used if empty.
+    #[serde(default)]
+    pub sensors: Vec<String>,
+
+    #[serde(default)]
+    pub style: TemperatureStyle,
+
+    /// Degrees Celsius shown as an empty thermometer.
+    #[serde(default = "super::default_temperature_min")]
+    pub min: i32,
+
+    /// Degrees Celsius shown as a full thermometer.
+    #[serde(default = "super::default_temperature_max")]
+    pub max: i32,
+
+    /// Blink at or above this temperature in degrees Celsius.
+    #[serde(default = "super::default_temperature_warning")]
+    pub warning: i32,
+
+    /// Fan speed shown as a full column.
+    #[serde(default = "super::default_max_rpm")]
+    pub max_rpm: u32,
+}
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandOutput {
+    /// A number if it parses as one, then an image, and text otherwise.
+    #[default]
+    Auto,
+    Number,
+    Text,
+    /// A path to an image file, or a base64-encoded image.
+    Image,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CommandWidgetDto {
+    /// Program and its arguments; use `["sh", "-c", "..."]` for shell pipelines.
+    pub command: Vec<String>,
+
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    #[serde(with = "humantime_serde", default = "super::default_command_timeout")]
+    pub timeout: Duration,
+
+    /// Maximum stdout size in bytes; larger outputs are treated as failures.
+    #[serde(default = "super::default_command_max_output")]
+    pub max_output: usize,
+
+    #[serde(default)]
+    pub output: CommandOutput,
+
+    /// The range of numeric outputs, rendered as a column.
+    #[serde(default)]
+    pub min: f64,
+    #[serde(default = "super::default_command_max")]
+    pub max: f64,
+}
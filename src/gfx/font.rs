@@ -0,0 +1,134 @@
+use image::GrayImage;
+
+use crate::gfx::set_pixel;
+
+pub const GLYPH_WIDTH: i32 = 3;
+pub const GLYPH_HEIGHT: i32 = 5;
+
+/// 3x5 glyphs, one byte per row, the 3 lowest bits are the pixels from left to right.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+pub fn draw_char(img: &mut GrayImage, x: i32, y: i32, c: char, brightness: u8) {
+    for (dy, row) in glyph(c).into_iter().enumerate() {
+        for dx in 0..GLYPH_WIDTH {
+            if row & (0b100 >> dx) != 0 {
+                set_pixel(img, x + dx, y + dy as i32, brightness);
+            }
+        }
+    }
+}
+
+/// Draws a single line of text with one pixel of spacing between glyphs.
+pub fn draw_text(img: &mut GrayImage, x: i32, y: i32, text: &str, brightness: u8) {
+    for (index, c) in text.chars().enumerate() {
+        draw_char(img, x + index as i32 * (GLYPH_WIDTH + 1), y, c, brightness);
+    }
+}
+
+/// Draws a number across the full 9-column width: two digits, or a narrow "1" followed by
+/// two digits for 100..=199. Larger values are capped at 199.
+pub fn draw_number(img: &mut GrayImage, y: i32, value: u32, brightness: u8) {
+    let value = value.min(199);
+    if value >= 100 {
+        for dy in 0..GLYPH_HEIGHT {
+            set_pixel(img, 0, y + dy, brightness);
+        }
+        draw_text(img, 2, y, &format!("{:02}", value - 100), brightness);
+    } else {
+        draw_text(img, 1, y, &format!("{value:02}"), brightness);
+    }
+}
+
+/// Draws text as centred lines of two characters, which is all that fits the width.
+/// Characters beyond the height of the frame are dropped.
+pub fn text_frame(text: &str, brightness: u8) -> GrayImage {
+    let mut img = crate::gfx::blank_frame();
+    let chars = text.chars().collect::<Vec<_>>();
+    let lines = chars.chunks(2).collect::<Vec<_>>();
+    let line_height = GLYPH_HEIGHT + 1;
+    let top = (img.height() as i32 - lines.len() as i32 * line_height + 1).max(0) / 2;
+
+    for (index, line) in lines.into_iter().enumerate() {
+        let line = line.iter().collect::<String>();
+        let x = if line.chars().count() == 1 { 3 } else { 1 };
+        draw_text(
+            &mut img,
+            x,
+            top + index as i32 * line_height,
+            &line,
+            brightness,
+        );
+    }
+    img
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+    use crate::gfx::blank_frame;
+
+    #[test]
+    fn test_draw_text() {
+        let mut img = blank_frame();
+        draw_text(&mut img, 1, 1, "10", 255);
+
+        let lit = |x, y| img.get_pixel(x, y) == &Luma([255]);
+        // the stem of "1"
+        assert!((1..6).all(|y| lit(2, y)));
+        // the left side of "0" starts after a one pixel gap
+        assert!(!lit(4, 3));
+        assert!((1..6).all(|y| lit(5, y)));
+        assert!(!lit(6, 3));
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::VecDeque;
+
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+
+use crate::gfx::{blank_frame, set_pixel, FULL};
+use crate::hw::HEIGHT;
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphStyle {
+    /// One horizontal bar per value, stacked top to bottom.
+    #[default]
+    Bars,
+    /// Scrolling history, one row per sample with the newest at the bottom.
+    Sparkline,
+}
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Scale {
+    #[default]
+    Linear,
+    Log,
+}
+
+impl Scale {
+    /// Maps `value` in `0..=max` to `0..=1`.
+    pub fn normalize(&self, value: f32, max: f32) -> f32 {
+        if max <= 0.0 {
+            return 0.0;
+        }
+        let normalized = match self {
+            Scale::Linear => value / max,
+            Scale::Log => value.ln_1p() / max.ln_1p(),
+        };
+        normalized.clamp(0.0, 1.0)
+    }
+}
+
+/// A fixed-size window of samples, one per matrix row.
+#[derive(Debug, Default)]
+pub struct History {
+    values: VecDeque<f32>,
+}
+
+impl History {
+    pub fn push(&mut self, value: f32) {
+        if self.values.len() == HEIGHT {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    pub fn max(&self) -> f32 {
+        self.values.iter().copied().fold(0.0, f32::max)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        self.values.iter().copied()
+    }
+}
+
+/// Draws a horizontal bar of `value` (0..=1) filling `width` pixels; the last partially
+/// covered pixel gets a proportional brightness.
+fn draw_hbar(img: &mut GrayImage, x: i32, y: i32, width: i32, height: i32, value: f32) {
+    let filled = value.clamp(0.0, 1.0) * width as f32;
+    for dx in 0..width {
+        let coverage = (filled - dx as f32).clamp(0.0, 1.0);
+        if coverage <= 0.0 {
+            break;
+        }
+        let brightness = (coverage * FULL as f32).round() as u8;
+        for dy in 0..height {
+            set_pixel(img, x + dx, y + dy, brightness);
+        }
+    }
+}
+
+/// Stacks one bar per value vertically, splitting the height evenly with one-row gaps.
+pub fn draw_bars(img: &mut GrayImage, values: &[f32]) {
+    if values.is_empty() {
+        return;
+    }
+    let count = values.len() as i32;
+    let height = img.height() as i32;
+    let gap = if count * 2 - 1 <= height { 1 } else { 0 };
+    let bar_height = ((height + gap) / count - gap).max(1);
+
+    for (index, &value) in values.iter().enumerate() {
+        let y = index as i32 * (bar_height + gap);
+        draw_hbar(img, 0, y, img.width() as i32, bar_height, value);
+    }
+}
+
+/// Width of each of `count` vertical strips separated by one-column gaps.
+fn strip_width(img: &GrayImage, count: usize) -> i32 {
+    ((img.width() as i32 + 1) / count.max(1) as i32 - 1).max(1)
+}
+
+/// Draws one vertical column per value, filled from the bottom, side by side.
+pub fn draw_columns(img: &mut GrayImage, values: &[f32]) {
+    let strip_width = strip_width(img, values.len());
+    let height = img.height() as i32;
+
+    for (index, &value) in values.iter().enumerate() {
+        let x = index as i32 * (strip_width + 1);
+        let filled = value.clamp(0.0, 1.0) * height as f32;
+        for dy in 0..height {
+            let coverage = (filled - dy as f32).clamp(0.0, 1.0);
+            if coverage <= 0.0 {
+                break;
+            }
+            let brightness = (coverage * FULL as f32).round() as u8;
+            for dx in 0..strip_width {
+                set_pixel(img, x + dx, height - 1 - dy, brightness);
+            }
+        }
+    }
+}
+
+/// Draws each history as a vertical strip, the strips are separated by a one-column gap.
+/// Values are expected to be normalized to 0..=1.
+pub fn draw_sparklines(img: &mut GrayImage, series: &[Vec<f32>]) {
+    let strip_width = strip_width(img, series.len());
+    let height = img.height() as i32;
+
+    for (index, values) in series.iter().enumerate() {
+        let x = index as i32 * (strip_width + 1);
+        let y = height - values.len() as i32;
+        for (row, &value) in values.iter().enumerate() {
+            draw_hbar(img, x, y + row as i32, strip_width, 1, value);
+        }
+    }
+}
+
+/// Renders a set of normalized values either as bars or as a sparkline.
+#[derive(Debug, Default)]
+pub struct Graph {
+    style: GraphStyle,
+    histories: Vec<History>,
+}
+
+impl Graph {
+    pub fn new(style: GraphStyle) -> Self {
+        Graph {
+            style,
+            histories: vec![],
+        }
+    }
+
+    /// Draws the values as bars, or a single sparkline of their mean.
+    pub fn draw(&mut self, values: &[f32]) -> GrayImage {
+        match self.style {
+            GraphStyle::Bars => self.draw_each(values),
+            GraphStyle::Sparkline => {
+                let mean = values.iter().sum::<f32>() / values.len().max(1) as f32;
+                self.draw_each(&[mean])
+            }
+        }
+    }
+
+    /// Draws the values as bars, or one sparkline per value side by side.
+    pub fn draw_each(&mut self, values: &[f32]) -> GrayImage {
+        let mut img = blank_frame();
+        match self.style {
+            GraphStyle::Bars => draw_bars(&mut img, values),
+            GraphStyle::Sparkline => {
+                self.histories.resize_with(values.len(), History::default);
+                for (history, &value) in self.histories.iter_mut().zip(values) {
+                    history.push(value);
+                }
+                let series: Vec<Vec<f32>> = self
+                    .histories
+                    .iter()
+                    .map(|history| history.iter().collect())
+                    .collect();
+                draw_sparklines(&mut img, &series);
+            }
+        }
+        img
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+
+    #[test]
+    fn test_draw_bars() {
+        let mut img = blank_frame();
+        draw_bars(&mut img, &[1.0, 0.5]);
+
+        // 34 rows, 2 bars with a single gap row: 16 rows each
+        assert_eq!(img.get_pixel(8, 15), &Luma([FULL]));
+        assert_eq!(img.get_pixel(0, 16), &Luma([0]));
+        assert_eq!(img.get_pixel(3, 17), &Luma([FULL]));
+        // 4.5 pixels out of 9: the fifth one is half lit
+        assert_eq!(img.get_pixel(4, 17), &Luma([128]));
+        assert_eq!(img.get_pixel(5, 17), &Luma([0]));
+    }
+
+    #[test]
+    fn test_sparkline_per_value() {
+        let mut graph = Graph::new(GraphStyle::Sparkline);
+        graph.draw_each(&[1.0, 0.0]);
+        let img = graph.draw_each(&[0.0, 1.0]);
+
+        // two strips of 4 columns with a gap column in between
+        assert_eq!(img.get_pixel(3, 32), &Luma([FULL]));
+        assert_eq!(img.get_pixel(5, 32), &Luma([0]));
+        assert_eq!(img.get_pixel(0, 33), &Luma([0]));
+        assert_eq!(img.get_pixel(4, 33), &Luma([0]));
+        assert_eq!(img.get_pixel(8, 33), &Luma([FULL]));
+    }
+
+    #[test]
+    fn test_sparkline_scrolls() {
+        let mut graph = Graph::new(GraphStyle::Sparkline);
+        graph.draw(&[1.0]);
+        let img = graph.draw(&[0.0, 0.0]);
+
+        assert_eq!(img.get_pixel(8, 32), &Luma([FULL]));
+        assert_eq!(img.get_pixel(0, 33), &Luma([0]));
+        assert_eq!(img.get_pixel(0, 31), &Luma([0]));
+    }
+}
@@ -0,0 +1,39 @@
+use image::{GrayImage, Luma};
+
+use crate::hw::{HEIGHT, WIDTH};
+
+pub mod font;
+pub mod graph;
+pub mod transition;
+
+pub const FULL: u8 = 255;
+pub const DIM: u8 = 64;
+
+pub fn blank_frame() -> GrayImage {
+    GrayImage::new(WIDTH as u32, HEIGHT as u32)
+}
+
+/// A boxed cross in the middle of the frame, shown when a frame source fails.
+pub fn error_frame() -> GrayImage {
+    let mut img = blank_frame();
+    for i in 0..WIDTH as i32 {
+        let y = 12 + i;
+        set_pixel(&mut img, i, 12, FULL);
+        set_pixel(&mut img, i, 20, FULL);
+        set_pixel(&mut img, 0, y, FULL);
+        set_pixel(&mut img, 8, y, FULL);
+        if (2..7).contains(&i) {
+            set_pixel(&mut img, i, y, FULL);
+            set_pixel(&mut img, 8 - i, y, FULL);
+        }
+    }
+    img
+}
+
+/// Sets a pixel, silently ignoring coordinates outside the image.
+pub fn set_pixel(img: &mut GrayImage, x: i32, y: i32, brightness: u8) {
+    if x < 0 || y < 0 || x >= img.width() as i32 || y >= img.height() as i32 {
+        return;
+    }
+    img.put_pixel(x as u32, y as u32, Luma([brightness]));
+}
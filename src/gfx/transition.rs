@@ -0,0 +1,283 @@
+use std::time::Duration;
+
+use anyhow::bail;
+use image::{GrayImage, Luma};
+use serde::{Deserialize, Serialize};
+
+/// A transition blocks its port while it plays, so it is kept short.
+pub const MAX_FPS: u32 = 60;
+pub const MAX_DURATION: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+    /// Switch frames immediately.
+    #[default]
+    None,
+    Crossfade,
+    WipeUp,
+    WipeDown,
+    WipeLeft,
+    WipeRight,
+    SlideUp,
+    SlideDown,
+    SlideLeft,
+    SlideRight,
+    Dissolve,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Transition {
+    #[serde(default)]
+    pub kind: TransitionKind,
+
+    #[serde(with = "humantime_serde", default = "default_duration")]
+    pub duration: Duration,
+
+    #[serde(default = "default_fps")]
+    pub fps: u32,
+}
+
+fn default_duration() -> Duration {
+    Duration::from_millis(300)
+}
+
+fn default_fps() -> u32 {
+    30
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Transition {
+            kind: TransitionKind::None,
+            duration: default_duration(),
+            fps: default_fps(),
+        }
+    }
+}
+
+/// Per-request transition settings; missing fields fall back to the configured transition.
+#[derive(Debug, Copy, Clone, Default, Deserialize)]
+pub struct TransitionOverride {
+    #[serde(default)]
+    pub kind: Option<TransitionKind>,
+
+    #[serde(with = "humantime_serde", default)]
+    pub duration: Option<Duration>,
+
+    #[serde(default)]
+    pub fps: Option<u32>,
+}
+
+impl TransitionOverride {
+    pub fn apply(&self, base: Transition) -> anyhow::Result<Transition> {
+        let transition = Transition {
+            kind: self.kind.unwrap_or(base.kind),
+            duration: self.duration.unwrap_or(base.duration),
+            fps: self.fps.unwrap_or(base.fps),
+        };
+        transition.validate()?;
+        Ok(transition)
+    }
+}
+
+impl Transition {
+    pub fn is_none(&self) -> bool {
+        self.kind == TransitionKind::None || self.duration.is_zero() || self.fps == 0
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.fps > MAX_FPS {
+            bail!("Transition fps must be at most {MAX_FPS}; got {}", self.fps);
+        }
+        if self.duration > MAX_DURATION {
+            bail!(
+                "Transition duration must be at most {MAX_DURATION:?}; got {:?}",
+                self.duration
+            );
+        }
+        Ok(())
+    }
+
+    pub fn frame_time(&self) -> Duration {
+        Duration::from_secs(1) / self.fps.max(1)
+    }
+
+    /// Intermediate frames between `from` and `to`, excluding both of them. Each frame is
+    /// only computed when it is needed.
+    pub fn frames<'a>(
+        &'a self,
+        from: &'a GrayImage,
+        to: &'a GrayImage,
+    ) -> impl Iterator<Item = GrayImage> + 'a {
+        let count = if self.is_none() || from.dimensions() != to.dimensions() {
+            0
+        } else {
+            (self.duration.as_secs_f32() * self.fps as f32).round() as u32
+        };
+        (1..count).map(move |step| self.blend(from, to, step as f32 / count as f32))
+    }
+
+    fn blend(&self, from: &GrayImage, to: &GrayImage, progress: f32) -> GrayImage {
+        let (width, height) = from.dimensions();
+        let (w, h) = (width as f32, height as f32);
+        // new content enters after `edge` pixels along the axis, scaled by progress
+        let edge = |size: f32| (progress * size).round() as u32;
+
+        GrayImage::from_fn(width, height, |x, y| {
+            let old = *from.get_pixel(x, y);
+            let new = *to.get_pixel(x, y);
+            match self.kind {
+                TransitionKind::None => new,
+                TransitionKind::Crossfade => {
+                    let Luma([a]) = old;
+                    let Luma([b]) = new;
+                    Luma([(a as f32 + (b as f32 - a as f32) * progress).round() as u8])
+                }
+                TransitionKind::WipeDown => pick(y < edge(h), new, old),
+                TransitionKind::WipeUp => pick(y >= height - edge(h), new, old),
+                TransitionKind::WipeRight => pick(x < edge(w), new, old),
+                TransitionKind::WipeLeft => pick(x >= width - edge(w), new, old),
+                TransitionKind::SlideDown => {
+                    let offset = edge(h);
+                    if y < offset {
+                        *to.get_pixel(x, height - offset + y)
+                    } else {
+                        *from.get_pixel(x, y - offset)
+                    }
+                }
+                TransitionKind::SlideUp => {
+                    let offset = edge(h);
+                    if y >= height - offset {
+                        *to.get_pixel(x, y + offset - height)
+                    } else {
+                        *from.get_pixel(x, y + offset)
+                    }
+                }
+                TransitionKind::SlideRight => {
+                    let offset = edge(w);
+                    if x < offset {
+                        *to.get_pixel(width - offset + x, y)
+                    } else {
+                        *from.get_pixel(x - offset, y)
+                    }
+                }
+                TransitionKind::SlideLeft => {
+                    let offset = edge(w);
+                    if x >= width - offset {
+                        *to.get_pixel(x + offset - width, y)
+                    } else {
+                        *from.get_pixel(x + offset, y)
+                    }
+                }
+                TransitionKind::Dissolve => pick(dissolve_threshold(x, y) < progress, new, old),
+            }
+        })
+    }
+}
+
+fn pick(condition: bool, new: Luma<u8>, old: Luma<u8>) -> Luma<u8> {
+    if condition {
+        new
+    } else {
+        old
+    }
+}
+
+/// A stable pseudo-random value in 0..1 per pixel, so dissolved pixels stay switched.
+fn dissolve_threshold(x: u32, y: u32) -> f32 {
+    let mut hash = (x.wrapping_mul(73_856_093) ^ y.wrapping_mul(19_349_663)).wrapping_add(1);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0x5bd1_e995);
+    hash ^= hash >> 15;
+    (hash % 1024) as f32 / 1024.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::blank_frame;
+
+    fn transition(kind: TransitionKind) -> Transition {
+        Transition {
+            kind,
+            duration: Duration::from_millis(100),
+            fps: 40,
+        }
+    }
+
+    fn white() -> GrayImage {
+        GrayImage::from_pixel(9, 34, Luma([255]))
+    }
+
+    #[test]
+    fn test_frame_count() {
+        let (from, to) = (blank_frame(), white());
+        let crossfade = transition(TransitionKind::Crossfade);
+        let frames = crossfade.frames(&from, &to).collect::<Vec<_>>();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[1].get_pixel(0, 0), &Luma([128]));
+        assert_eq!(
+            transition(TransitionKind::None).frames(&from, &to).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_wipe_and_slide() {
+        let from = blank_frame();
+        let mut to = white();
+        to.put_pixel(0, 33, Luma([7]));
+
+        let wipe = transition(TransitionKind::WipeDown).blend(&from, &to, 0.5);
+        assert_eq!(wipe.get_pixel(0, 16), &Luma([255]));
+        assert_eq!(wipe.get_pixel(0, 17), &Luma([0]));
+
+        // the bottom row of the new frame enters first
+        let slide = transition(TransitionKind::SlideDown).blend(&from, &to, 1.0 / 34.0);
+        assert_eq!(slide.get_pixel(0, 0), &Luma([7]));
+        assert_eq!(slide.get_pixel(1, 0), &Luma([255]));
+        assert_eq!(slide.get_pixel(0, 1), &Luma([0]));
+
+        let slide = transition(TransitionKind::SlideLeft).blend(&from, &to, 1.0 / 9.0);
+        assert_eq!(slide.get_pixel(8, 0), &Luma([255]));
+        assert_eq!(slide.get_pixel(7, 0), &Luma([0]));
+    }
+
+    #[test]
+    fn test_dissolve_accumulates() {
+        let (from, to) = (blank_frame(), white());
+        let dissolve = transition(TransitionKind::Dissolve);
+        let frames = dissolve.frames(&from, &to).collect::<Vec<_>>();
+        let lit = |img: &GrayImage| img.pixels().filter(|&&Luma([p])| p > 0).count();
+        assert!(lit(&frames[0]) < lit(&frames[1]));
+        assert!(lit(&frames[1]) < lit(&frames[2]));
+        assert!(frames[0]
+            .enumerate_pixels()
+            .all(|(x, y, &Luma([p]))| p == 0 || frames[1].get_pixel(x, y) == &Luma([255])));
+    }
+
+    #[test]
+    fn test_override() {
+        let base = transition(TransitionKind::Crossfade);
+        let applied = TransitionOverride {
+            kind: Some(TransitionKind::WipeLeft),
+            ..Default::default()
+        }
+        .apply(base)
+        .unwrap();
+        assert_eq!(applied.kind, TransitionKind::WipeLeft);
+        assert_eq!(applied.duration, base.duration);
+
+        let too_long = TransitionOverride {
+            duration: Some(Duration::from_secs(3600)),
+            ..Default::default()
+        };
+        assert!(too_long.apply(base).is_err());
+        let too_fast = TransitionOverride {
+            fps: Some(1000),
+            ..Default::default()
+        };
+        assert!(too_fast.apply(base).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::hw::{HEIGHT, WIDTH};
+
+pub const B1_WIDTH: usize = 300;
+pub const B1_HEIGHT: usize = 400;
+
+/// The Framework 16 input modules, which share the serial protocol but not the commands.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceKind {
+    /// 9x34 LEDs with 256 brightness levels
+    #[default]
+    LedMatrix,
+    /// 300x400 monochrome display
+    B1Display,
+    /// A single RGB LED
+    C1Minimal,
+}
+
+impl DeviceKind {
+    /// Width and height of the frames the device displays.
+    pub fn size(self) -> (u32, u32) {
+        match self {
+            DeviceKind::LedMatrix => (WIDTH as u32, HEIGHT as u32),
+            DeviceKind::B1Display => (B1_WIDTH as u32, B1_HEIGHT as u32),
+            DeviceKind::C1Minimal => (1, 1),
+        }
+    }
+}
+
+impl Display for DeviceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceKind::LedMatrix => write!(f, "led_matrix"),
+            DeviceKind::B1Display => write!(f, "b1_display"),
+            DeviceKind::C1Minimal => write!(f, "c1_minimal"),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serialport::{SerialPort, TTYPort};
+
+use crate::hw::firmware::FirmwareState;
+
+/// An emulated LED matrix on the master side of a pseudo-terminal pair. `Port` talks to
+/// `path()` exactly like it talks to `/dev/ttyACM0`.
+pub struct Emulator {
+    path: String,
+    state: Arc<Mutex<FirmwareState>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    // Keeps the terminal alive while `Port` closes and reopens it
+    _slave: TTYPort,
+}
+
+impl Emulator {
+    pub fn spawn() -> anyhow::Result<Self> {
+        let (mut master, slave) = TTYPort::pair()?;
+        let path = slave
+            .name()
+            .ok_or_else(|| anyhow::anyhow!("Pseudo-terminal has no name"))?;
+        master.set_timeout(Duration::from_millis(20))?;
+
+        let state = Arc::new(Mutex::new(FirmwareState::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = thread::Builder::new().name("emulator".to_string()).spawn({
+            let state = state.clone();
+            let stop = stop.clone();
+            move || Self::run(master, state, stop)
+        })?;
+
+        Ok(Emulator {
+            path,
+            state,
+            stop,
+            handle: Some(handle),
+            _slave: slave,
+        })
+    }
+
+    fn run(mut master: TTYPort, state: Arc<Mutex<FirmwareState>>, stop: Arc<AtomicBool>) {
+        let mut buffer = vec![];
+        let mut chunk = [0; 256];
+
+        while !stop.load(Ordering::Relaxed) {
+            match master.read(&mut chunk) {
+                Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => continue,
+                Err(_) => break,
+            }
+
+            let mut response = vec![];
+            let Ok(mut state) = state.lock() else {
+                break;
+            };
+            state.execute_all(&mut buffer, &mut response);
+            drop(state);
+
+            if !response.is_empty() && master.write_all(&response).is_err() {
+                break;
+            }
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, FirmwareState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GrayImage;
+
+    use super::*;
+    use crate::config::port_dto::PortDto;
+    use crate::hw::port::Port;
+    use crate::hw::{HEIGHT, WIDTH};
+
+    fn port(emulator: &Emulator, keep_open: bool) -> Port {
+        let port_dto: PortDto = toml::from_str(&format!(
+            "path = \"{}\"\ntimeout = \"1s\"\nkeep_open = {keep_open}",
+            emulator.path()
+        ))
+        .unwrap();
+        Port::try_from(port_dto).unwrap()
+    }
+
+    #[test]
+    fn test_version() {
+        let emulator = Emulator::spawn().unwrap();
+        emulator.state().version.patch = 7;
+
+        let version = port(&emulator, true).get_device_version().unwrap();
+        assert_eq!(version.minor, 2);
+        assert_eq!(version.patch, 7);
+    }
+
+    #[test]
+    fn test_display_gray_image() {
+        let emulator = Emulator::spawn().unwrap();
+        let mut port = port(&emulator, false);
+
+        let mut image = GrayImage::new(WIDTH as u32, HEIGHT as u32);
+        image.put_pixel(3, 10, image::Luma([200]));
+        port.display_gray_image(image.clone()).unwrap();
+        // Version is a round trip, so every command before it has been processed
+        port.get_device_version().unwrap();
+
+        let state = emulator.state();
+        assert_eq!(state.frame_image(), image);
+        assert_eq!(state.columns_received, WIDTH);
+        assert_eq!(state.commits, 1);
+    }
+}
@@ -0,0 +1,200 @@
+use image::GrayImage;
+
+use crate::hw::device_version::DeviceVersion;
+use crate::hw::{Command, FWK_MAGIC, HEIGHT, WIDTH};
+
+/// The LED matrix firmware protocol, interpreted in software. Backs the terminal simulator
+/// and the test emulator, and is inspectable from tests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirmwareState {
+    pub version: DeviceVersion,
+    /// Columns received with `SendCol` since the last `CommitCols`
+    pub pending: [[u8; HEIGHT]; WIDTH],
+    /// What the panel displays
+    pub frame: [[u8; HEIGHT]; WIDTH],
+    pub brightness: u8,
+    pub sleeping: bool,
+    pub pattern: Option<u8>,
+    pub columns_received: usize,
+    pub commits: usize,
+    /// Every command id in the order of arrival, including unsupported ones
+    pub commands: Vec<u8>,
+}
+
+impl Default for FirmwareState {
+    fn default() -> Self {
+        FirmwareState {
+            version: DeviceVersion {
+                major: 0,
+                minor: 2,
+                patch: 0,
+                pre_release: false,
+            },
+            pending: [[0; HEIGHT]; WIDTH],
+            frame: [[0; HEIGHT]; WIDTH],
+            brightness: 51,
+            sleeping: false,
+            pattern: None,
+            columns_received: 0,
+            commits: 0,
+            commands: vec![],
+        }
+    }
+}
+
+impl FirmwareState {
+    pub fn frame_image(&self) -> GrayImage {
+        GrayImage::from_fn(WIDTH as u32, HEIGHT as u32, |x, y| {
+            image::Luma([self.frame[x as usize][y as usize]])
+        })
+    }
+
+    /// Executes every complete command in `buffer` and removes them from it, appending the
+    /// responses of queries to `response`.
+    pub fn execute_all(&mut self, buffer: &mut Vec<u8>, response: &mut Vec<u8>) {
+        // Skip garbage until the next command
+        while let Some(start) = buffer.windows(FWK_MAGIC.len()).position(|w| w == FWK_MAGIC) {
+            buffer.drain(..start);
+            match self.execute(buffer, response) {
+                Some(used) => drop(buffer.drain(..used)),
+                None => break,
+            }
+        }
+    }
+
+    /// Executes the command at the start of `buffer` and returns the number of bytes it used,
+    /// or `None` if the command is not complete yet. The firmware protocol has no framing:
+    /// commands like `Brightness` are queries without an argument and setters with one, so
+    /// an argument is only assumed if more bytes have already arrived.
+    fn execute(&mut self, buffer: &[u8], response: &mut Vec<u8>) -> Option<usize> {
+        let (&command, args) = buffer.get(FWK_MAGIC.len()..)?.split_first()?;
+        let header = FWK_MAGIC.len() + 1;
+        let optional_arg = args
+            .first()
+            .filter(|_| !args.starts_with(&FWK_MAGIC[..args.len().min(FWK_MAGIC.len())]))
+            .copied();
+
+        let used = match command {
+            c if c == Command::SendCol as u8 => {
+                if args.len() < HEIGHT + 1 {
+                    return None;
+                }
+                if let Some(column) = self.pending.get_mut(args[0] as usize) {
+                    column.copy_from_slice(&args[1..HEIGHT + 1]);
+                }
+                self.columns_received += 1;
+                header + HEIGHT + 1
+            }
+            c if c == Command::CommitCols as u8 => {
+                self.frame = self.pending;
+                self.commits += 1;
+                header
+            }
+            c if c == Command::Version as u8 => {
+                let version = &self.version;
+                let mut bytes = [0; 32];
+                bytes[0] = version.major;
+                bytes[1] = (version.minor << 4) | (version.patch & 0x0F);
+                bytes[2] = version.pre_release as u8;
+                response.extend_from_slice(&bytes);
+                header
+            }
+            c if c == Command::Brightness as u8 => match optional_arg {
+                Some(brightness) => {
+                    self.brightness = brightness;
+                    header + 1
+                }
+                None => {
+                    response.push(self.brightness);
+                    header
+                }
+            },
+            c if c == Command::Sleeping as u8 => match optional_arg {
+                Some(sleeping) => {
+                    self.sleeping = sleeping != 0;
+                    header + 1
+                }
+                None => {
+                    response.push(self.sleeping as u8);
+                    header
+                }
+            },
+            c if c == Command::Pattern as u8 => {
+                self.pattern = Some(*args.first()?);
+                header + 1
+            }
+            _ => {
+                // The argument length of other commands is unknown: they end where the next
+                // command starts, and a trailing magic byte may start one
+                let end = args
+                    .windows(FWK_MAGIC.len())
+                    .position(|window| window == FWK_MAGIC)
+                    .unwrap_or_else(|| args.len() - args.ends_with(&FWK_MAGIC[..1]) as usize);
+                header + end
+            }
+        };
+        self.commands.push(command);
+
+        Some(used)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execute_optional_args() {
+        let mut state = FirmwareState::default();
+        let mut response = vec![];
+
+        let set_brightness = [0x32, 0xAC, Command::Brightness as u8, 100];
+        assert_eq!(state.execute(&set_brightness, &mut response), Some(4));
+        assert_eq!(state.brightness, 100);
+
+        let get_brightness = [0x32, 0xAC, Command::Brightness as u8, 0x32, 0xAC];
+        assert_eq!(state.execute(&get_brightness, &mut response), Some(3));
+        assert_eq!(response, vec![100]);
+
+        let partial_col = [0x32, 0xAC, Command::SendCol as u8, 0, 1, 2];
+        assert_eq!(state.execute(&partial_col, &mut response), None);
+    }
+
+    #[test]
+    fn test_execute_all() {
+        let mut state = FirmwareState::default();
+        let mut response = vec![];
+        let mut buffer = vec![
+            0xFF,
+            0x32,
+            0xAC,
+            Command::CommitCols as u8,
+            0x32,
+            0xAC,
+            0x07,
+        ];
+
+        state.execute_all(&mut buffer, &mut response);
+        assert_eq!(state.commits, 1);
+        assert_eq!(buffer, [0x32, 0xAC, 0x07]);
+    }
+
+    #[test]
+    fn test_unknown_command_keeps_the_next() {
+        let mut state = FirmwareState::default();
+        let mut response = vec![];
+        let mut buffer = vec![0x32, 0xAC, Command::SetColor as u8, 1, 2, 3, 0x32];
+
+        state.execute_all(&mut buffer, &mut response);
+        assert_eq!(buffer, [0x32]);
+        buffer.extend_from_slice(&[0xAC, Command::CommitCols as u8]);
+        state.execute_all(&mut buffer, &mut response);
+
+        assert_eq!(state.commits, 1);
+        assert_eq!(
+            state.commands,
+            [Command::SetColor as u8, Command::CommitCols as u8]
+        );
+        assert!(buffer.is_empty());
+    }
+}
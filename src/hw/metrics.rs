@@ -0,0 +1,108 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+/// Counters of a single port, shared with the API without locking the port itself.
+#[derive(Debug, Default)]
+pub struct PortMetrics {
+    frames_committed: AtomicU64,
+    frames_skipped: AtomicU64,
+    full_refreshes: AtomicU64,
+    columns_sent: AtomicU64,
+    columns_skipped: AtomicU64,
+    frames_dropped: AtomicU64,
+    consecutive_failures: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct PortMetricsSnapshot {
+    pub frames_committed: u64,
+    pub frames_skipped: u64,
+    pub full_refreshes: u64,
+    pub columns_sent: u64,
+    pub columns_skipped: u64,
+    pub frames_dropped: u64,
+    pub consecutive_failures: u64,
+    pub last_error: Option<String>,
+}
+
+impl PortMetrics {
+    pub fn record_frame(&self, columns_sent: u64, columns_skipped: u64, full_refresh: bool) {
+        if columns_sent == 0 {
+            self.frames_skipped.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.frames_committed.fetch_add(1, Ordering::Relaxed);
+        }
+        if full_refresh {
+            self.full_refreshes.fetch_add(1, Ordering::Relaxed);
+        }
+        self.columns_sent.fetch_add(columns_sent, Ordering::Relaxed);
+        self.columns_skipped
+            .fetch_add(columns_skipped, Ordering::Relaxed);
+    }
+
+    pub fn record_dropped(&self) {
+        self.frames_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Tracks the failure state of the port: the last error is kept until the next success.
+    pub fn record_result<T, E: std::fmt::Display>(&self, result: &Result<T, E>) {
+        let last_error = match result {
+            Ok(_) => {
+                self.consecutive_failures.store(0, Ordering::Relaxed);
+                None
+            }
+            Err(err) => {
+                self.consecutive_failures.fetch_add(1, Ordering::Relaxed);
+                Some(err.to_string())
+            }
+        };
+        if let Ok(mut current) = self.last_error.lock() {
+            *current = last_error;
+        }
+    }
+
+    pub fn snapshot(&self) -> PortMetricsSnapshot {
+        PortMetricsSnapshot {
+            frames_committed: self.frames_committed.load(Ordering::Relaxed),
+            frames_skipped: self.frames_skipped.load(Ordering::Relaxed),
+            full_refreshes: self.full_refreshes.load(Ordering::Relaxed),
+            columns_sent: self.columns_sent.load(Ordering::Relaxed),
+            columns_skipped: self.columns_skipped.load(Ordering::Relaxed),
+            frames_dropped: self.frames_dropped.load(Ordering::Relaxed),
+            consecutive_failures: self.consecutive_failures.load(Ordering::Relaxed),
+            last_error: self.last_error.lock().ok().and_then(|err| err.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_frame() {
+        let metrics = PortMetrics::default();
+        metrics.record_frame(9, 0, true);
+        metrics.record_frame(2, 7, false);
+        metrics.record_frame(0, 9, false);
+        metrics.record_dropped();
+        metrics.record_result::<(), _>(&Err("broken pipe"));
+
+        assert_eq!(
+            metrics.snapshot(),
+            PortMetricsSnapshot {
+                frames_committed: 2,
+                frames_skipped: 1,
+                full_refreshes: 1,
+                columns_sent: 11,
+                columns_skipped: 16,
+                frames_dropped: 1,
+                consecutive_failures: 1,
+                last_error: Some("broken pipe".to_string()),
+            }
+        );
+    }
+}
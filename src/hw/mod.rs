@@ -1,12 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+pub mod device_kind;
 pub mod device_version;
+#[cfg(test)]
+pub mod emulator;
+pub mod firmware;
+pub mod metrics;
+pub mod orientation;
 pub mod port;
+pub mod splash;
+pub mod terminal;
+pub mod transport;
+pub mod worker;
 
 pub const FWK_MAGIC: &[u8] = &[0x32, 0xAC];
 
-const WIDTH: usize = 9;
-const HEIGHT: usize = 34;
+pub const WIDTH: usize = 9;
+pub const HEIGHT: usize = 34;
 
-#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum Pattern {
     Percentage = 0,
@@ -0,0 +1,151 @@
+use std::io;
+
+use anyhow::anyhow;
+use image::{imageops, GrayImage};
+
+use crate::config::port_dto::PortDto;
+use crate::hw::{HEIGHT, WIDTH};
+
+/// Maps logical images to the physical 9x34 column layout of a module.
+/// Flips are applied first, then the image is rotated clockwise by `rotation` degrees.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Orientation {
+    rotation: u16,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+}
+
+impl TryFrom<&PortDto> for Orientation {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &PortDto) -> Result<Self, Self::Error> {
+        if ![0, 90, 180, 270].contains(&value.rotation) {
+            return Err(anyhow!(
+                "Rotation must be one of 0, 90, 180 or 270; got {}",
+                value.rotation
+            ));
+        }
+
+        Ok(Orientation {
+            rotation: value.rotation,
+            flip_horizontal: value.flip_horizontal,
+            flip_vertical: value.flip_vertical,
+        })
+    }
+}
+
+impl Orientation {
+    fn is_landscape(&self) -> bool {
+        self.rotation == 90 || self.rotation == 270
+    }
+
+    /// Width and height of the logical images.
+    pub fn size(&self) -> (u32, u32) {
+        if self.is_landscape() {
+            (HEIGHT as u32, WIDTH as u32)
+        } else {
+            (WIDTH as u32, HEIGHT as u32)
+        }
+    }
+
+    /// Landscape ports take 34x9 images. Images that are already 9x34 are only flipped,
+    /// so portrait content such as widgets keeps working on them.
+    pub fn apply(&self, img: GrayImage) -> io::Result<GrayImage> {
+        let landscape = img.dimensions() == (HEIGHT as u32, WIDTH as u32);
+        if self.is_landscape() && !landscape && img.dimensions() != (WIDTH as u32, HEIGHT as u32) {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                anyhow!(
+                    "Image must be {HEIGHT}x{WIDTH} or {WIDTH}x{HEIGHT} pixels; got {}x{}",
+                    img.width(),
+                    img.height()
+                ),
+            ));
+        }
+
+        let mut img = img;
+        if self.flip_horizontal {
+            imageops::flip_horizontal_in_place(&mut img);
+        }
+        if self.flip_vertical {
+            imageops::flip_vertical_in_place(&mut img);
+        }
+
+        Ok(match self.rotation {
+            90 if landscape => imageops::rotate90(&img),
+            270 if landscape => imageops::rotate270(&img),
+            180 => {
+                imageops::rotate180_in_place(&mut img);
+                img
+            }
+            _ => img,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+    use crate::gfx::blank_frame;
+
+    fn orientation(rotation: u16, flip_horizontal: bool, flip_vertical: bool) -> Orientation {
+        Orientation {
+            rotation,
+            flip_horizontal,
+            flip_vertical,
+        }
+    }
+
+    fn marked(width: u32, height: u32) -> GrayImage {
+        let mut img = GrayImage::new(width, height);
+        img.put_pixel(0, 0, Luma([255]));
+        img
+    }
+
+    #[test]
+    fn test_portrait() -> io::Result<()> {
+        let img = orientation(180, false, false).apply(marked(9, 34))?;
+        assert_eq!(img.get_pixel(8, 33), &Luma([255]));
+
+        let img = orientation(0, true, false).apply(marked(9, 34))?;
+        assert_eq!(img.get_pixel(8, 0), &Luma([255]));
+
+        let img = orientation(0, false, true).apply(marked(9, 34))?;
+        assert_eq!(img.get_pixel(0, 33), &Luma([255]));
+
+        // rotation and flips cancel out
+        let img = orientation(180, true, true).apply(marked(9, 34))?;
+        assert_eq!(img.get_pixel(0, 0), &Luma([255]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_landscape() -> io::Result<()> {
+        let img = orientation(90, false, false).apply(marked(34, 9))?;
+        assert_eq!(img.dimensions(), (9, 34));
+        assert_eq!(img.get_pixel(8, 0), &Luma([255]));
+
+        let img = orientation(270, false, false).apply(marked(34, 9))?;
+        assert_eq!(img.get_pixel(0, 33), &Luma([255]));
+
+        // portrait images pass through landscape ports
+        let img = orientation(90, false, false).apply(marked(9, 34))?;
+        assert_eq!(img.get_pixel(0, 0), &Luma([255]));
+
+        assert!(orientation(90, false, false)
+            .apply(GrayImage::new(10, 10))
+            .is_err());
+        // portrait ports leave the geometry check to the port
+        assert_eq!(
+            orientation(0, false, false).apply(marked(34, 9))?,
+            marked(34, 9)
+        );
+        assert_eq!(
+            orientation(0, false, false).apply(blank_frame())?,
+            blank_frame()
+        );
+        Ok(())
+    }
+}
@@ -1,73 +1,116 @@
 use std::io;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::anyhow;
 use image::{GrayImage, ImageReader, Luma};
-use serialport::SerialPort;
-use tracing::warn;
 
 use crate::config::port_dto::PortDto;
+use crate::gfx::transition::Transition;
+use crate::hw::device_kind::{DeviceKind, B1_HEIGHT};
 use crate::hw::device_version::DeviceVersion;
-use crate::hw::{Command, FWK_MAGIC, HEIGHT, WIDTH};
+use crate::hw::metrics::PortMetrics;
+use crate::hw::orientation::Orientation;
+use crate::hw::transport::{SerialTransport, Transport};
+use crate::hw::{Command, Pattern, FWK_MAGIC};
 
 #[derive(Debug)]
 pub struct Port {
-    path: Arc<str>,
-    baud_rate: u32,
-    timeout: Duration,
-    port: Option<Box<dyn SerialPort>>,
+    transport: Box<dyn Transport>,
+    kind: DeviceKind,
     keep_open: bool,
-    wait_delay: Option<Duration>,
+    orientation: Orientation,
+    color: [u8; 3],
+    /// The frame on the device, in its physical layout; `None` if the device state is unknown
+    current_frame: Option<GrayImage>,
+    /// The image behind `current_frame`, as it was submitted
+    current_image: Option<GrayImage>,
+    full_refresh_every: Option<u32>,
+    frames_since_full_refresh: u32,
+    metrics: Arc<PortMetrics>,
 }
 
-impl TryFrom<PortDto> for Port {
+/// The validated settings of a port, independent of its transport.
+#[derive(Debug, Clone)]
+pub struct PortSettings {
+    kind: DeviceKind,
+    keep_open: bool,
+    orientation: Orientation,
+    color: [u8; 3],
+    full_refresh_every: Option<u32>,
+}
+
+impl TryFrom<&PortDto> for PortSettings {
     type Error = anyhow::Error;
 
-    fn try_from(value: PortDto) -> Result<Self, Self::Error> {
-        let path: Arc<str> = Arc::from(value.path);
+    fn try_from(value: &PortDto) -> Result<Self, Self::Error> {
+        let orientation = Orientation::try_from(value)?;
+        if value.kind != DeviceKind::LedMatrix && orientation != Orientation::default() {
+            return Err(anyhow!(
+                "Rotation and flips are only supported by led_matrix ports, not {}",
+                value.kind
+            ));
+        }
+        if value.kind != DeviceKind::C1Minimal && value.color.is_some() {
+            return Err(anyhow!(
+                "Colors are only supported by c1_minimal ports, not {}",
+                value.kind
+            ));
+        }
 
-        Ok(Port {
-            path,
-            port: None,
-            baud_rate: value.baud_rate,
-            timeout: value.timeout,
+        Ok(PortSettings {
+            kind: value.kind,
             keep_open: value.keep_open,
-            wait_delay: value.wait_delay,
+            orientation,
+            color: value.color.unwrap_or([255; 3]),
+            full_refresh_every: value.full_refresh_every,
         })
     }
 }
 
+impl TryFrom<PortDto> for Port {
+    type Error = anyhow::Error;
+
+    fn try_from(value: PortDto) -> Result<Self, Self::Error> {
+        let settings = PortSettings::try_from(&value)?;
+        Ok(Port::new(Box::new(SerialTransport::from(&value)), settings))
+    }
+}
+
 impl Port {
-    fn open(&mut self) -> serialport::Result<&mut Box<dyn SerialPort>> {
-        let port = &mut self.port;
-        if let Some(port) = port {
-            return Ok(port);
+    pub fn new(transport: Box<dyn Transport>, settings: PortSettings) -> Self {
+        Port {
+            transport,
+            kind: settings.kind,
+            keep_open: settings.keep_open,
+            orientation: settings.orientation,
+            color: settings.color,
+            current_frame: None,
+            current_image: None,
+            full_refresh_every: settings.full_refresh_every,
+            frames_since_full_refresh: 0,
+            metrics: Arc::default(),
         }
+    }
 
-        loop {
-            let error = match serialport::new(self.path.as_ref(), self.baud_rate)
-                .timeout(self.timeout)
-                .open()
-            {
-                Ok(next_port) => {
-                    port.replace(next_port);
-                    return Ok(port.as_mut().unwrap());
-                }
-                Err(err) => err,
-            };
-
-            if let Some(delay) = self.wait_delay {
-                warn!(?error, port = %self.path.as_ref(), "Failed to open port");
-                std::thread::sleep(delay);
-            } else {
-                return Err(error);
-            }
+    pub fn metrics(&self) -> Arc<PortMetrics> {
+        self.metrics.clone()
+    }
+
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+
+    /// Width and height of the images the port takes.
+    pub fn size(&self) -> (u32, u32) {
+        match self.kind {
+            DeviceKind::LedMatrix => self.orientation.size(),
+            kind => kind.size(),
         }
     }
 
     pub fn close(&mut self) {
-        self.port.take();
+        self.transport.close();
     }
 
     fn prepare_command_buffer(&mut self, command: Command, args: &[u8]) -> [u8; 64] {
@@ -80,7 +123,8 @@ impl Port {
     }
     fn write_command(&mut self, command: Command, data: &[u8]) -> io::Result<()> {
         let buffer = self.prepare_command_buffer(command, data);
-        self.open()?.write_all(&buffer[..3 + data.len()])?;
+        self.transport.open()?;
+        self.transport.write_all(&buffer[..3 + data.len()])?;
         if !self.keep_open {
             self.close();
         }
@@ -94,10 +138,9 @@ impl Port {
         read_buffer: &mut [u8],
     ) -> io::Result<()> {
         let buffer = self.prepare_command_buffer(command, data);
-        let port = self.open()?;
-
-        port.write_all(&buffer[..3 + data.len()])?;
-        port.read_exact(read_buffer)?;
+        self.transport.open()?;
+        self.transport.write_all(&buffer[..3 + data.len()])?;
+        self.transport.read_exact(read_buffer)?;
 
         if !self.keep_open {
             self.close();
@@ -135,6 +178,53 @@ impl Port {
         self.write_command(Command::CommitCols, &[])
     }
 
+    /// Writes a column to the device buffer; it is shown after `commit_columns`.
+    fn send_column(&mut self, index: usize, vals: &[u8]) -> io::Result<()> {
+        match self.kind {
+            DeviceKind::LedMatrix => self.send_col(index as u8, vals),
+            DeviceKind::B1Display => {
+                let mut buffer = (index as u16).to_le_bytes().to_vec();
+                buffer.extend_from_slice(vals);
+                self.write_command(Command::SetPixelColumn, &buffer)
+            }
+            // A single pixel, shown as soon as it is set
+            DeviceKind::C1Minimal => self.write_command(Command::SetColor, vals),
+        }
+    }
+
+    fn commit_columns(&mut self) -> io::Result<()> {
+        match self.kind {
+            DeviceKind::LedMatrix => self.commit_cols(),
+            DeviceKind::B1Display => self.write_command(Command::FlushFramebuffer, &[]),
+            DeviceKind::C1Minimal => Ok(()),
+        }
+    }
+
+    fn unsupported(&self, operation: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            anyhow!("{operation} are not supported by {} ports", self.kind),
+        )
+    }
+
+    /// Replaces the frame with one of the built-in firmware patterns.
+    pub fn show_pattern(&mut self, pattern: Pattern) -> io::Result<()> {
+        if self.kind != DeviceKind::LedMatrix {
+            return Err(self.unsupported("Patterns"));
+        }
+        // The device no longer shows the last frame, so the next one is sent in full
+        self.current_frame = None;
+        match pattern {
+            Pattern::Percentage => self.write_command(Command::Pattern, &[pattern as u8, 100]),
+            _ => self.write_command(Command::Pattern, &[pattern as u8]),
+        }
+    }
+
+    pub fn set_sleeping(&mut self, sleeping: bool) -> io::Result<()> {
+        self.current_frame = None;
+        self.write_command(Command::Sleeping, &[sleeping as u8])
+    }
+
     #[allow(dead_code)]
     pub fn display_gray_image_by_path(&mut self, image_path: &str) -> anyhow::Result<()> {
         let img = ImageReader::open(image_path)?.decode()?.to_luma8();
@@ -142,43 +232,327 @@ impl Port {
         Ok(())
     }
 
-    pub fn display_gray_image(&mut self, img: GrayImage) -> io::Result<()> {
+    fn validate_geometry(&self, img: &GrayImage) -> io::Result<()> {
+        let (expected_width, expected_height) = self.kind.size();
         let width = img.width();
         let height = img.height();
 
-        if width != WIDTH as u32 || height != HEIGHT as u32 {
+        if width != expected_width || height != expected_height {
             return Err(io::Error::new(
                 io::ErrorKind::Other,
-                anyhow!("Image must be {WIDTH}x{HEIGHT} pixels; got {width}x{height}"),
+                anyhow!(
+                    "Image must be {expected_width}x{expected_height} pixels; got {width}x{height}"
+                ),
             ));
         }
+        Ok(())
+    }
+
+    pub fn display_gray_image(&mut self, img: GrayImage) -> io::Result<()> {
+        self.display_with_transition(img, &Transition::default())
+    }
+
+    /// The column as the device expects it: a brightness per LED on the LED matrix, a bit
+    /// per pixel on the B1 display (set if the pixel is at least half bright), and the
+    /// configured color scaled by the brightness on the C1 module.
+    fn column(&self, img: &GrayImage, col: usize) -> Vec<u8> {
+        let pixels = (0..img.height()).map(|row| {
+            let &Luma([pixel]) = img.get_pixel(col as u32, row);
+            pixel
+        });
+        match self.kind {
+            DeviceKind::LedMatrix => pixels.collect(),
+            DeviceKind::B1Display => {
+                let mut bits = vec![0; B1_HEIGHT / 8];
+                for (row, pixel) in pixels.enumerate() {
+                    if pixel >= 128 {
+                        bits[row / 8] |= 1 << (row % 8);
+                    }
+                }
+                bits
+            }
+            DeviceKind::C1Minimal => {
+                let brightness = pixels.max().unwrap_or_default() as u16;
+                self.color
+                    .iter()
+                    .map(|&channel| (channel as u16 * brightness / 255) as u8)
+                    .collect()
+            }
+        }
+    }
 
-        let mut brightnesses = [0; HEIGHT];
-        for col in 0..WIDTH {
-            for (row, brightness) in brightnesses.iter_mut().enumerate() {
-                let &Luma([pixel]) = img.get_pixel(col as u32, row as u32);
-                *brightness = pixel;
+    /// Sends only the columns that differ from the last committed frame, and nothing at all
+    /// if the frame did not change.
+    fn display_physical(&mut self, img: GrayImage) -> io::Result<()> {
+        self.validate_geometry(&img)?;
+
+        let full_refresh = self.current_frame.is_none()
+            || self
+                .full_refresh_every
+                .is_some_and(|every| self.frames_since_full_refresh + 1 >= every);
+        let previous = if full_refresh {
+            None
+        } else {
+            self.current_frame.take()
+        };
+
+        let width = img.width() as usize;
+        let mut columns_sent = 0;
+        for col in 0..width {
+            let column = self.column(&img, col);
+            if previous
+                .as_ref()
+                .is_some_and(|previous| self.column(previous, col) == column)
+            {
+                continue;
             }
-            self.send_col(col as u8, &brightnesses)?;
+            // The device state is unknown after a failure, so the next frame is sent in full
+            self.current_frame = None;
+            self.send_column(col, &column)?;
+            columns_sent += 1;
+        }
+        if columns_sent > 0 {
+            self.commit_columns()?;
         }
-        self.commit_cols()?;
+
+        self.metrics.record_frame(
+            columns_sent,
+            (width as u64).saturating_sub(columns_sent),
+            full_refresh,
+        );
+        if full_refresh {
+            self.frames_since_full_refresh = 0;
+        } else {
+            self.frames_since_full_refresh += 1;
+        }
+        self.current_frame = Some(img);
 
         Ok(())
     }
+
+    /// Plays the intermediate frames from the currently displayed frame to `img` before
+    /// displaying it. Without a known current frame this is the same as `display_gray_image`.
+    pub fn display_with_transition(
+        &mut self,
+        img: GrayImage,
+        transition: &Transition,
+    ) -> io::Result<()> {
+        let physical = self.orientation.apply(img.clone())?;
+        self.validate_geometry(&physical)?;
+
+        // Computed on the submitted images, so wipes and slides move the way they were
+        // asked to on rotated and flipped ports
+        let current_image = self.current_image.take();
+        if let (Some(_), Some(current_image)) = (&self.current_frame, current_image) {
+            let frame_time = transition.frame_time();
+            for frame in transition.frames(&current_image, &img) {
+                let start = Instant::now();
+                let frame = self.orientation.apply(frame)?;
+                self.display_physical(frame)?;
+                std::thread::sleep(frame_time.saturating_sub(start.elapsed()));
+            }
+        }
+
+        self.display_physical(physical)?;
+        self.current_image = Some(img);
+        Ok(())
+    }
 }
 
-#[cfg(all(test, feature = "integration"))]
+#[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::hw::transport::mock::MockTransport;
+    use crate::hw::{HEIGHT, WIDTH};
+
+    fn mock_port(keep_open: bool) -> (Port, MockTransport) {
+        let port_dto: PortDto =
+            toml::from_str(&format!("path = \"mock\"\nkeep_open = {keep_open}")).unwrap();
+        let transport = MockTransport::default();
+        let settings = PortSettings::try_from(&port_dto).unwrap();
+        (Port::new(Box::new(transport.clone()), settings), transport)
+    }
+
+    #[test]
+    fn test_display_gray_image() {
+        let (mut port, transport) = mock_port(true);
+        let mut image = GrayImage::new(WIDTH as u32, HEIGHT as u32);
+        image.put_pixel(0, 1, Luma([255]));
+
+        port.display_gray_image(image.clone()).unwrap();
+        {
+            let state = transport.state();
+            let send_col = 3 + 1 + HEIGHT;
+            assert_eq!(state.written.len(), WIDTH * send_col + 3);
+            assert_eq!(
+                state.written[..6],
+                [0x32, 0xAC, Command::SendCol as u8, 0, 0, 255]
+            );
+            assert_eq!(
+                state.written[WIDTH * send_col..],
+                [0x32, 0xAC, Command::CommitCols as u8]
+            );
+            assert_eq!((state.opens, state.closes), (1, 0));
+        }
+
+        // Nothing changed, nothing is sent
+        transport.state().written.clear();
+        port.display_gray_image(image).unwrap();
+        assert!(transport.state().written.is_empty());
+    }
+
+    #[test]
+    fn test_get_device_version() {
+        let (mut port, transport) = mock_port(false);
+        let mut response = vec![1, 0x23, 1];
+        response.resize(32, 0);
+        transport.state().responses.extend(response);
+
+        let version = port.get_device_version().unwrap();
+        assert_eq!(version.to_string(), "1.2.3-pre");
+        assert_eq!(
+            transport.state().written,
+            [0x32, 0xAC, Command::Version as u8]
+        );
+        // Not kept open
+        let state = transport.state();
+        assert_eq!((state.opens, state.closes), (1, 1));
+        drop(state);
+
+        assert!(port.get_device_version().is_err());
+    }
+
+    #[test]
+    fn test_pattern_forces_full_refresh() {
+        let (mut port, transport) = mock_port(true);
+        let image = GrayImage::new(WIDTH as u32, HEIGHT as u32);
+        port.display_gray_image(image.clone()).unwrap();
+
+        port.show_pattern(Pattern::Zigzag).unwrap();
+        port.set_sleeping(false).unwrap();
+        transport.state().written.clear();
+        port.display_gray_image(image).unwrap();
+        assert_eq!(
+            transport.state().written.len(),
+            WIDTH * (3 + 1 + HEIGHT) + 3
+        );
+    }
+
+    #[test]
+    fn test_transition_follows_rotation() {
+        let (mut port, transport) = mock_module("rotation = 180");
+        port.display_gray_image(GrayImage::new(WIDTH as u32, HEIGHT as u32))
+            .unwrap();
+        transport.state().written.clear();
+
+        let slide_left: Transition = toml::from_str(
+            r#"
+            kind = "slide_left"
+            duration = "90ms"
+            fps = 100
+            "#,
+        )
+        .unwrap();
+        let white = GrayImage::from_pixel(WIDTH as u32, HEIGHT as u32, Luma([255]));
+        port.display_with_transition(white, &slide_left).unwrap();
+
+        // The new image enters on the right, which is the physical left on a rotated port
+        assert_eq!(
+            transport.state().written[..6],
+            [0x32, 0xAC, Command::SendCol as u8, 0, 255, 255]
+        );
+    }
+
+    fn mock_module(settings: &str) -> (Port, MockTransport) {
+        let port_dto: PortDto = toml::from_str(&format!("path = \"mock\"\n{settings}")).unwrap();
+        let transport = MockTransport::default();
+        let settings = PortSettings::try_from(&port_dto).unwrap();
+        (Port::new(Box::new(transport.clone()), settings), transport)
+    }
+
+    #[test]
+    fn test_b1_display() {
+        let (mut port, transport) = mock_module(r#"kind = "b1_display""#);
+        assert_eq!(port.size(), (300, 400));
+        assert!(port.display_gray_image(GrayImage::new(9, 34)).is_err());
+
+        let mut image = GrayImage::new(300, 400);
+        image.put_pixel(0, 9, Luma([200]));
+        image.put_pixel(0, 10, Luma([100]));
+        port.display_gray_image(image.clone()).unwrap();
+        {
+            let state = transport.state();
+            let set_column = 3 + 2 + 50;
+            assert_eq!(state.written.len(), 300 * set_column + 3);
+            assert_eq!(
+                state.written[..7],
+                [0x32, 0xAC, Command::SetPixelColumn as u8, 0, 0, 0, 0b10]
+            );
+            assert_eq!(
+                state.written[set_column..set_column + 5],
+                [0x32, 0xAC, Command::SetPixelColumn as u8, 1, 0]
+            );
+            assert_eq!(
+                state.written[300 * set_column..],
+                [0x32, 0xAC, Command::FlushFramebuffer as u8]
+            );
+        }
+
+        // Only the changed column is sent
+        transport.state().written.clear();
+        image.put_pixel(299, 0, Luma([255]));
+        port.display_gray_image(image).unwrap();
+        assert_eq!(
+            transport.state().written[..6],
+            [0x32, 0xAC, Command::SetPixelColumn as u8, 43, 1, 1]
+        );
+
+        let err = port.show_pattern(Pattern::Zigzag).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn test_c1_minimal() {
+        let (mut port, transport) = mock_module("kind = \"c1_minimal\"\ncolor = [255, 128, 0]");
+        port.display_gray_image(GrayImage::from_pixel(1, 1, Luma([128])))
+            .unwrap();
+        assert_eq!(
+            transport.state().written,
+            [0x32, 0xAC, Command::SetColor as u8, 128, 64, 0]
+        );
+
+        let port_dto: PortDto =
+            toml::from_str("path = \"mock\"\nkind = \"c1_minimal\"\nrotation = 90").unwrap();
+        assert!(PortSettings::try_from(&port_dto).is_err());
+        let port_dto: PortDto = toml::from_str("path = \"mock\"\ncolor = [1, 2, 3]").unwrap();
+        assert!(PortSettings::try_from(&port_dto).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "integration"))]
+mod integration_tests {
+    use std::time::Duration;
+
     use super::*;
 
     fn get_port() -> Port {
+        let transport = SerialTransport::new(
+            "/dev/ttyACM0",
+            115200,
+            Duration::from_secs(20),
+            Some(Duration::from_millis(10)),
+        );
         Port {
-            path: Arc::from("/dev/ttyACM0"),
-            baud_rate: 115200,
-            timeout: Duration::from_secs(20),
-            port: None,
+            transport: Box::new(transport),
+            kind: DeviceKind::LedMatrix,
             keep_open: true,
-            wait_delay: Some(Duration::from_millis(10)),
+            orientation: Orientation::default(),
+            color: [255; 3],
+            current_frame: None,
+            current_image: None,
+            full_refresh_every: None,
+            frames_since_full_refresh: 0,
+            metrics: Arc::default(),
         }
     }
 
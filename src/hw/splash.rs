@@ -0,0 +1,84 @@
+use anyhow::Context;
+use image::GrayImage;
+
+use crate::config::splash_dto::SplashActionDto;
+use crate::gfx::font::text_frame;
+use crate::gfx::transition::Transition;
+use crate::gfx::{blank_frame, FULL};
+use crate::hw::worker::PortWorker;
+use crate::hw::Pattern;
+
+/// What is shown on startup and shutdown; images are loaded once when the config is read.
+#[derive(Debug, Clone)]
+pub enum SplashAction {
+    Frame(GrayImage),
+    Pattern(Pattern),
+    Sleep,
+}
+
+impl TryFrom<SplashActionDto> for SplashAction {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SplashActionDto) -> Result<Self, Self::Error> {
+        Ok(match value {
+            SplashActionDto::Image(path) => SplashAction::Frame(
+                image::open(&path)
+                    .with_context(|| format!("Failed to load splash image {path:?}"))?
+                    .to_luma8(),
+            ),
+            SplashActionDto::Pattern(pattern) => SplashAction::Pattern(pattern),
+            SplashActionDto::Text(text) => SplashAction::Frame(text_frame(&text, FULL)),
+            SplashActionDto::Blank => SplashAction::Frame(blank_frame()),
+            SplashActionDto::Sleep => SplashAction::Sleep,
+        })
+    }
+}
+
+impl SplashAction {
+    pub async fn show(&self, port: &PortWorker) -> anyhow::Result<()> {
+        match self {
+            SplashAction::Frame(frame) => {
+                port.submit_waiting(frame.clone(), Transition::default())
+                    .await?
+                    .await
+            }
+            SplashAction::Pattern(pattern) => port.show_pattern(*pattern).await,
+            SplashAction::Sleep => port.set_sleeping(true).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::config::matrix_dto::MatrixName;
+    use crate::config::port_dto::PortDto;
+    use crate::hw::port::{Port, PortSettings};
+    use crate::hw::transport::mock::MockTransport;
+
+    #[tokio::test]
+    async fn test_splash_waits_for_queued_frames() -> anyhow::Result<()> {
+        let transport = MockTransport::default();
+        transport.state().write_delay = Some(Duration::from_millis(5));
+        let settings = PortSettings::try_from(&PortDto::new("mock".to_string()))?;
+        let port = Port::new(Box::new(transport.clone()), settings);
+        let worker = PortWorker::spawn(MatrixName::left(), port, 1);
+
+        // One frame being written, one waiting in the full queue
+        let first = worker
+            .submit_waiting(blank_frame(), Transition::default())
+            .await?;
+        let second = worker
+            .submit_waiting(text_frame("A", FULL), Transition::default())
+            .await?;
+        SplashAction::Pattern(Pattern::AllOn).show(&worker).await?;
+        first.await?;
+        second.await?;
+
+        let written = transport.state().written.clone();
+        assert!(written.ends_with(&[0x32, 0xAC, 0x01, Pattern::AllOn as u8]));
+        Ok(())
+    }
+}
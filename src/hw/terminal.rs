@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use image::{GrayImage, Luma};
+
+use crate::config::matrix_dto::MatrixName;
+use crate::hw::firmware::FirmwareState;
+use crate::hw::transport::Transport;
+use crate::hw::{HEIGHT, WIDTH};
+
+/// The simulated panels, redrawn side by side on stderr whenever one of them commits a frame.
+/// Panels are drawn in the order they were added.
+#[derive(Debug, Default)]
+pub struct Terminal {
+    frames: Mutex<Vec<(MatrixName, Option<GrayImage>)>>,
+    /// Keeps the frames without writing them to stderr
+    headless: bool,
+}
+
+impl Terminal {
+    fn add(&self, name: &MatrixName) {
+        if let Ok(mut frames) = self.frames.lock() {
+            if !frames.iter().any(|(added, _)| added == name) {
+                frames.push((name.clone(), None));
+            }
+        }
+    }
+
+    /// Removes the panels of matrices that are not configured anymore.
+    pub fn retain(&self, names: &[MatrixName]) {
+        if let Ok(mut frames) = self.frames.lock() {
+            frames.retain(|(name, _)| names.contains(name));
+        }
+    }
+
+    /// The names of the panels, in drawing order.
+    #[cfg(test)]
+    pub fn names(&self) -> Vec<MatrixName> {
+        self.frames
+            .lock()
+            .map(|frames| frames.iter().map(|(name, _)| name.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// The last frame committed to a panel.
+    #[cfg(test)]
+    pub fn frame(&self, name: &MatrixName) -> Option<GrayImage> {
+        let frames = self.frames.lock().ok()?;
+        let (_, frame) = frames.iter().find(|(added, _)| added == name)?;
+        frame.clone()
+    }
+
+    fn draw(&self, name: &MatrixName, frame: GrayImage) {
+        let Ok(mut frames) = self.frames.lock() else {
+            return;
+        };
+        if let Some((_, drawn)) = frames.iter_mut().find(|(added, _)| added == name) {
+            drawn.replace(frame);
+        }
+        if self.headless {
+            return;
+        }
+
+        let mut screen = String::from("\x1b[H");
+        for (name, frame) in frames.iter() {
+            if frame.is_some() {
+                let _ = write!(screen, "{:<width$}  ", name.to_string(), width = WIDTH * 2);
+            }
+        }
+        screen.push_str("\x1b[K\n");
+
+        // Every character shows two pixels: the upper half is the foreground of ▀, the lower
+        // half the background; each is two characters wide to keep the aspect ratio
+        for row in (0..HEIGHT as u32).step_by(2) {
+            for frame in frames.iter().filter_map(|(_, frame)| frame.as_ref()) {
+                for col in 0..WIDTH as u32 {
+                    let &Luma([upper]) = frame.get_pixel(col, row);
+                    let &Luma([lower]) = frame.get_pixel(col, row + 1);
+                    let _ = write!(
+                        screen,
+                        "\x1b[38;2;{upper};{upper};{upper}m\x1b[48;2;{lower};{lower};{lower}m▀▀"
+                    );
+                }
+                screen.push_str("\x1b[0m  ");
+            }
+            screen.push_str("\x1b[K\n");
+        }
+        screen.push_str("\x1b[J");
+
+        let mut stderr = io::stderr().lock();
+        let _ = stderr.write_all(screen.as_bytes());
+        let _ = stderr.flush();
+    }
+}
+
+/// Interprets the firmware protocol in software and shows the committed frames in the
+/// terminal instead of writing to a serial port.
+#[derive(Debug)]
+pub struct TerminalTransport {
+    name: MatrixName,
+    terminal: Arc<Terminal>,
+    firmware: FirmwareState,
+    buffer: Vec<u8>,
+    response: VecDeque<u8>,
+}
+
+impl TerminalTransport {
+    pub fn new(name: MatrixName, terminal: Arc<Terminal>) -> Self {
+        terminal.add(&name);
+        TerminalTransport {
+            name,
+            terminal,
+            firmware: FirmwareState::default(),
+            buffer: vec![],
+            response: VecDeque::new(),
+        }
+    }
+}
+
+impl Transport for TerminalTransport {
+    fn open(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        let commits = self.firmware.commits;
+        let mut response = vec![];
+
+        self.buffer.extend_from_slice(data);
+        self.firmware.execute_all(&mut self.buffer, &mut response);
+        self.response.extend(response);
+
+        if self.firmware.commits != commits {
+            self.terminal.draw(&self.name, self.firmware.frame_image());
+        }
+        Ok(())
+    }
+
+    fn read_exact(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+        if self.response.len() < buffer.len() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        for byte in buffer.iter_mut() {
+            *byte = self.response.pop_front().unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::port_dto::PortDto;
+    use crate::hw::port::{Port, PortSettings};
+
+    #[test]
+    fn test_frames_reach_the_terminal() -> anyhow::Result<()> {
+        let terminal = Arc::new(Terminal {
+            headless: true,
+            ..Terminal::default()
+        });
+        let name = MatrixName::left();
+        let transport = TerminalTransport::new(name.clone(), terminal.clone());
+        let settings = PortSettings::try_from(&PortDto::new("simulated".to_string()))?;
+        let mut port = Port::new(Box::new(transport), settings);
+        assert_eq!(terminal.frame(&name), None);
+
+        let mut image = GrayImage::new(WIDTH as u32, HEIGHT as u32);
+        image.put_pixel(3, 20, Luma([200]));
+        port.display_gray_image(image.clone())?;
+        assert_eq!(terminal.frame(&name), Some(image));
+
+        terminal.retain(&[MatrixName::right()]);
+        assert!(terminal.names().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt::Debug;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serialport::SerialPort;
+use tracing::warn;
+
+use crate::config::port_dto::PortDto;
+
+/// The byte stream `Port` speaks the firmware protocol over.
+pub trait Transport: Debug + Send {
+    /// Opens the transport unless it is open already.
+    fn open(&mut self) -> io::Result<()>;
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()>;
+    fn read_exact(&mut self, buffer: &mut [u8]) -> io::Result<()>;
+    fn close(&mut self);
+}
+
+#[derive(Debug)]
+pub struct SerialTransport {
+    path: Arc<str>,
+    baud_rate: u32,
+    timeout: Duration,
+    wait_delay: Option<Duration>,
+    port: Option<Box<dyn SerialPort>>,
+}
+
+impl From<&PortDto> for SerialTransport {
+    fn from(value: &PortDto) -> Self {
+        SerialTransport::new(
+            value.path.as_str(),
+            value.baud_rate,
+            value.timeout,
+            value.wait_delay,
+        )
+    }
+}
+
+impl SerialTransport {
+    pub fn new(
+        path: impl Into<Arc<str>>,
+        baud_rate: u32,
+        timeout: Duration,
+        wait_delay: Option<Duration>,
+    ) -> Self {
+        SerialTransport {
+            path: path.into(),
+            baud_rate,
+            timeout,
+            wait_delay,
+            port: None,
+        }
+    }
+
+    fn port(&mut self) -> io::Result<&mut Box<dyn SerialPort>> {
+        self.open()?;
+        self.port
+            .as_mut()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))
+    }
+}
+
+impl Transport for SerialTransport {
+    fn open(&mut self) -> io::Result<()> {
+        if self.port.is_some() {
+            return Ok(());
+        }
+
+        loop {
+            let error = match serialport::new(self.path.as_ref(), self.baud_rate)
+                .timeout(self.timeout)
+                .open()
+            {
+                Ok(port) => {
+                    self.port.replace(port);
+                    return Ok(());
+                }
+                Err(err) => err,
+            };
+
+            if let Some(delay) = self.wait_delay {
+                warn!(?error, port = %self.path.as_ref(), "Failed to open port");
+                std::thread::sleep(delay);
+            } else {
+                return Err(error.into());
+            }
+        }
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.port()?.write_all(data)
+    }
+
+    fn read_exact(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+        self.port()?.read_exact(buffer)
+    }
+
+    fn close(&mut self) {
+        self.port.take();
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct MockState {
+        pub is_open: bool,
+        pub opens: usize,
+        pub closes: usize,
+        /// Everything written to the transport, in order
+        pub written: Vec<u8>,
+        /// Bytes handed out to reads
+        pub responses: VecDeque<u8>,
+        /// Makes every open, read and write fail with this kind of error
+        pub fail_with: Option<io::ErrorKind>,
+        /// Makes every write take this long, like a slow device
+        pub write_delay: Option<Duration>,
+    }
+
+    /// An in-memory transport; clones share the state, so tests keep a handle to inspect
+    /// the byte stream after giving the transport away.
+    #[derive(Debug, Clone, Default)]
+    pub struct MockTransport {
+        pub state: Arc<Mutex<MockState>>,
+    }
+
+    impl MockTransport {
+        pub fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+            self.state.lock().unwrap()
+        }
+
+        fn check(state: &MockState) -> io::Result<()> {
+            if let Some(kind) = state.fail_with {
+                return Err(kind.into());
+            }
+            if !state.is_open {
+                return Err(io::ErrorKind::NotConnected.into());
+            }
+            Ok(())
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn open(&mut self) -> io::Result<()> {
+            let mut state = self.state();
+            if let Some(kind) = state.fail_with {
+                return Err(kind.into());
+            }
+            if !state.is_open {
+                state.is_open = true;
+                state.opens += 1;
+            }
+            Ok(())
+        }
+
+        fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+            let write_delay = self.state().write_delay;
+            if let Some(delay) = write_delay {
+                std::thread::sleep(delay);
+            }
+            let mut state = self.state();
+            Self::check(&state)?;
+            state.written.extend_from_slice(data);
+            Ok(())
+        }
+
+        fn read_exact(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+            let mut state = self.state();
+            Self::check(&state)?;
+            if state.responses.len() < buffer.len() {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            for byte in buffer.iter_mut() {
+                *byte = state.responses.pop_front().unwrap_or_default();
+            }
+            Ok(())
+        }
+
+        fn close(&mut self) {
+            let mut state = self.state();
+            if state.is_open {
+                state.is_open = false;
+                state.closes += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::future::Future;
+use std::io;
+use std::io::ErrorKind;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::anyhow;
+use image::GrayImage;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::oneshot;
+use tracing::{debug, error};
+
+use crate::config::matrix_dto::MatrixName;
+#[cfg(test)]
+use crate::config::port_dto::PortDto;
+use crate::gfx::transition::Transition;
+use crate::hw::device_kind::DeviceKind;
+use crate::hw::device_version::DeviceVersion;
+use crate::hw::metrics::PortMetrics;
+use crate::hw::port::Port;
+#[cfg(test)]
+use crate::hw::port::PortSettings;
+use crate::hw::Pattern;
+
+enum PortCommand {
+    Display {
+        image: GrayImage,
+        transition: Transition,
+        reply: oneshot::Sender<io::Result<()>>,
+    },
+    Pattern {
+        pattern: Pattern,
+        reply: oneshot::Sender<io::Result<()>>,
+    },
+    Sleep {
+        sleeping: bool,
+        reply: oneshot::Sender<io::Result<()>>,
+    },
+    GetDeviceVersion {
+        reply: oneshot::Sender<io::Result<DeviceVersion>>,
+    },
+    Close {
+        reply: oneshot::Sender<io::Result<()>>,
+    },
+}
+
+/// Owns a `Port` on a dedicated thread with its own queue. Commands sent to the same worker
+/// are executed strictly in order, and a slow or stuck device only ever blocks its own thread:
+/// once its queue is full, further commands for it are rejected instead of waiting.
+#[derive(Debug)]
+pub struct PortWorker {
+    name: MatrixName,
+    kind: DeviceKind,
+    size: (u32, u32),
+    sender: mpsc::Sender<PortCommand>,
+    metrics: Arc<PortMetrics>,
+    /// The last image displayed successfully, as it was submitted
+    frame: Arc<Mutex<Option<GrayImage>>>,
+}
+
+impl PortWorker {
+    /// Panics if the OS cannot start a thread, like `std::thread::spawn`.
+    pub fn spawn(name: MatrixName, port: Port, queue_size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(queue_size);
+        let metrics = port.metrics();
+        let kind = port.kind();
+        let size = port.size();
+        let frame = Arc::new(Mutex::new(None));
+        thread::Builder::new()
+            .name(format!("{name}-port"))
+            .spawn({
+                let frame = frame.clone();
+                move || Self::run(port, receiver, frame)
+            })
+            .expect("Failed to spawn a port thread");
+
+        PortWorker {
+            name,
+            kind,
+            size,
+            sender,
+            metrics,
+            frame,
+        }
+    }
+
+    fn run(
+        mut port: Port,
+        mut receiver: mpsc::Receiver<PortCommand>,
+        frame: Arc<Mutex<Option<GrayImage>>>,
+    ) {
+        while let Some(command) = receiver.blocking_recv() {
+            match command {
+                PortCommand::Display {
+                    image,
+                    transition,
+                    reply,
+                } => {
+                    let result = port.display_with_transition(image.clone(), &transition);
+                    port.metrics().record_result(&result);
+                    if result.is_ok() {
+                        if let Ok(mut frame) = frame.lock() {
+                            frame.replace(image);
+                        }
+                    }
+
+                    // We return ErrorKind::Other ourselves: stdlib does not use it, so we know that
+                    // something wrong with the port has happened, and we'll try our luck and release
+                    // the handle, so we would not interfere with kernel device numbering
+                    if let Err(err) = &result {
+                        if err.kind() != ErrorKind::Other {
+                            error!(?err, ?port, "Shutting down the port");
+                            port.close();
+                        }
+                    }
+                    let _ = reply.send(result);
+                }
+                PortCommand::Pattern { pattern, reply } => {
+                    if let Ok(mut frame) = frame.lock() {
+                        frame.take();
+                    }
+                    let _ = reply.send(port.show_pattern(pattern));
+                }
+                PortCommand::Sleep { sleeping, reply } => {
+                    let _ = reply.send(port.set_sleeping(sleeping));
+                }
+                PortCommand::GetDeviceVersion { reply } => {
+                    let _ = reply.send(port.get_device_version());
+                }
+                PortCommand::Close { reply } => {
+                    port.close();
+                    let _ = reply.send(Ok(()));
+                }
+            }
+        }
+
+        debug!(?port, "Port worker stopped");
+        port.close();
+    }
+
+    fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<io::Result<T>>) -> PortCommand,
+    ) -> anyhow::Result<impl Future<Output = anyhow::Result<T>>> {
+        let name = self.name.clone();
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .try_send(command(reply))
+            .map_err(|err| match err {
+                TrySendError::Full(_) => {
+                    self.metrics.record_dropped();
+                    anyhow!("{name} port queue is full")
+                }
+                TrySendError::Closed(_) => anyhow!("{name} port worker has stopped"),
+            })?;
+
+        Ok(Self::response(name, response))
+    }
+
+    /// Like `request`, but waits for room in a full queue instead of failing.
+    async fn request_waiting<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<io::Result<T>>) -> PortCommand,
+    ) -> anyhow::Result<impl Future<Output = anyhow::Result<T>>> {
+        let name = self.name.clone();
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send(command(reply))
+            .await
+            .map_err(|_| anyhow!("{name} port worker has stopped"))?;
+
+        Ok(Self::response(name, response))
+    }
+
+    async fn response<T>(
+        name: MatrixName,
+        response: oneshot::Receiver<io::Result<T>>,
+    ) -> anyhow::Result<T> {
+        let result = response
+            .await
+            .map_err(|_| anyhow!("{name} port worker has stopped"))?;
+        Ok(result?)
+    }
+
+    /// Queues the image without waiting for the device; the returned future resolves once
+    /// the image is displayed. Fails right away if the queue is full.
+    pub fn submit(
+        &self,
+        image: GrayImage,
+        transition: Transition,
+    ) -> anyhow::Result<impl Future<Output = anyhow::Result<()>>> {
+        self.request(move |reply| PortCommand::Display {
+            image,
+            transition,
+            reply,
+        })
+    }
+
+    /// Queues the image once there is room in the queue; the returned future resolves once
+    /// the image is displayed.
+    pub async fn submit_waiting(
+        &self,
+        image: GrayImage,
+        transition: Transition,
+    ) -> anyhow::Result<impl Future<Output = anyhow::Result<()>>> {
+        self.request_waiting(move |reply| PortCommand::Display {
+            image,
+            transition,
+            reply,
+        })
+        .await
+    }
+
+    /// Waits for room in a full queue, like `set_sleeping` and `close`, so splashes are not
+    /// lost behind frames still queued.
+    pub async fn show_pattern(&self, pattern: Pattern) -> anyhow::Result<()> {
+        self.request_waiting(|reply| PortCommand::Pattern { pattern, reply })
+            .await?
+            .await
+    }
+
+    pub async fn set_sleeping(&self, sleeping: bool) -> anyhow::Result<()> {
+        self.request_waiting(|reply| PortCommand::Sleep { sleeping, reply })
+            .await?
+            .await
+    }
+
+    /// Closes the port once everything queued before has been processed. The port is
+    /// reopened by the next command.
+    pub async fn close(&self) -> anyhow::Result<()> {
+        self.request_waiting(|reply| PortCommand::Close { reply })
+            .await?
+            .await
+    }
+
+    pub fn name(&self) -> &MatrixName {
+        &self.name
+    }
+
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+
+    /// Width and height of the images the port takes.
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn metrics(&self) -> Arc<PortMetrics> {
+        self.metrics.clone()
+    }
+
+    pub fn frame(&self) -> Option<GrayImage> {
+        self.frame.lock().ok().and_then(|frame| frame.clone())
+    }
+
+    pub async fn get_device_version(&self) -> anyhow::Result<DeviceVersion> {
+        self.request(|reply| PortCommand::GetDeviceVersion { reply })?
+            .await
+    }
+}
+
+#[cfg(test)]
+impl PortWorker {
+    pub fn mock(name: &str) -> (Self, crate::hw::transport::mock::MockTransport) {
+        let port_dto: PortDto = toml::from_str(r#"path = "mock""#).unwrap();
+        let transport = crate::hw::transport::mock::MockTransport::default();
+        let settings = PortSettings::try_from(&port_dto).unwrap();
+        let port = Port::new(Box::new(transport.clone()), settings);
+        (PortWorker::spawn(name.into(), port, 4), transport)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_errors_are_reported_back() {
+        let port_dto: PortDto = toml::from_str(r#"path = "/dev/does-not-exist""#).unwrap();
+        let worker = PortWorker::spawn(MatrixName::left(), Port::try_from(port_dto).unwrap(), 1);
+
+        let image = GrayImage::new(9, 34);
+        let display = worker.submit(image, Transition::default()).unwrap();
+        assert!(display.await.is_err());
+        // The worker survives a failed command
+        assert!(worker.get_device_version().await.is_err());
+        assert_eq!(worker.metrics.snapshot().consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_port_is_closed_on_io_errors() {
+        let (worker, transport) = PortWorker::mock("right");
+        let image = GrayImage::new(9, 34);
+        worker
+            .submit(image.clone(), Transition::default())
+            .unwrap()
+            .await
+            .unwrap();
+
+        // Bad geometry is our own ErrorKind::Other and leaves the port alone
+        let wrong_size = GrayImage::new(10, 10);
+        let display = worker.submit(wrong_size, Transition::default()).unwrap();
+        assert!(display.await.is_err());
+        assert_eq!(transport.state().closes, 0);
+
+        transport.state().fail_with = Some(io::ErrorKind::BrokenPipe);
+        let mut changed = image;
+        changed.put_pixel(0, 0, image::Luma([1]));
+        let display = worker.submit(changed, Transition::default()).unwrap();
+        assert!(display.await.is_err());
+        assert_eq!(transport.state().closes, 1);
+        // The last frame that made it to the device
+        assert_eq!(worker.frame(), Some(GrayImage::new(9, 34)));
+
+        transport.state().fail_with = None;
+        worker.get_device_version().await.ok();
+        worker.close().await.unwrap();
+        assert!(!transport.state().is_open);
+    }
+}
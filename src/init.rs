@@ -8,13 +8,25 @@ use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
-pub fn init_tracing() -> anyhow::Result<()> {
+/// `log_level` takes precedence over `RUST_LOG`.
+/// The filter directives in effect: `log_level`, else a valid `RUST_LOG`, else `info`.
+pub fn log_directives(log_level: Option<&str>) -> String {
+    match log_level {
+        Some(log_level) => log_level.to_string(),
+        None => env::var(EnvFilter::DEFAULT_ENV)
+            .ok()
+            .filter(|directives| EnvFilter::try_new(directives).is_ok())
+            .unwrap_or_else(|| "info".to_string()),
+    }
+}
+
+pub fn init_tracing(log_level: Option<&str>) -> anyhow::Result<()> {
     let console_layer = ConsoleLayer::builder().with_default_env().spawn();
     let fmt_layer = tracing_subscriber::fmt::layer()
         .compact()
         .with_ansi(atty::is(atty::Stream::Stdout))
         .with_target(false);
-    let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
+    let filter_layer = EnvFilter::try_new(log_directives(log_level))?;
 
     tracing_subscriber::registry()
         .with(filter_layer)
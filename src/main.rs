@@ -1,41 +1,71 @@
+use std::process::ExitCode;
 use std::sync::Arc;
 
 use actix_web::{web, App, HttpServer};
+use anyhow::Context;
 use clap::Parser;
+use futures_util::future::join_all;
+use kanal::{AsyncReceiver, AsyncSender};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::oneshot;
 use tokio::task::JoinSet;
 use tokio::time::Instant;
 use tracing::{debug, error, info, warn};
 
 use crate::api::base64::{render_base64, render_base64_multiple};
 use crate::api::files::render_files;
+use crate::api::frame::{get_frame, get_frames_json};
+use crate::api::metrics::port_metrics;
+use crate::api::render_task::{PortLanes, RenderTask};
+use crate::api::widgets::{disable_widget, enable_widget, list_widgets};
 use crate::api::AppState;
-use crate::cli::cmd_args::CmdArgs;
-use crate::config::led_matrix_config::LedMatrixConfig;
-use crate::config::lef_matrix_config_dto::LedMatrixConfigDto;
+use crate::cli::check_config::check_config;
+use crate::cli::cmd_args::{CmdArgs, Command};
+use crate::config::led_matrix_config::{LedMatrixConfig, SharedConfig};
+use crate::config::matrix_dto::MatrixName;
 use crate::init::{get_systemd_socket, init_tracing};
+use crate::widget::spawn_widgets;
 
 mod api;
 mod cli;
 mod config;
+mod gfx;
 mod hw;
 mod init;
+mod state;
+mod widget;
 
 #[actix_web::main]
-async fn main() -> anyhow::Result<()> {
-    init_tracing()?;
+async fn main() -> anyhow::Result<ExitCode> {
     let cmd_args = CmdArgs::parse();
+    if let Some(Command::CheckConfig { path, print }) = cmd_args.command.as_ref() {
+        return Ok(check_config(
+            path.as_deref().unwrap_or(&cmd_args.config),
+            &cmd_args.overrides,
+            cmd_args.log_level.as_deref(),
+            *print,
+        ));
+    }
+    init_tracing(cmd_args.log_level.as_deref())?;
 
-    let config = LedMatrixConfigDto::try_from(cmd_args.config.as_path())?;
+    let config = cmd_args.overrides.load(&cmd_args.config)?;
     let config = Arc::new(LedMatrixConfig::try_from(config)?);
-    config.log_led_matrix_versions()?;
+    config.log_led_matrix_versions().await?;
+    config.show_startup_splash().await;
+    if let Some(state) = config.state.as_ref() {
+        if let Err(err) = state.restore(&config).await {
+            warn!(?err, "Failed to restore the state");
+        }
+    }
 
     let unix_socket = config.unix_socket.clone();
     let listen_address = config.listen_address.clone();
 
     let (sender, receiver) = kanal::bounded_async(config.max_queue_size);
+    let shared_config = Arc::new(SharedConfig::new(config.clone()));
     let state = web::Data::new(AppState {
-        sender,
-        config: config.clone(),
+        sender: sender.clone(),
+        config: shared_config.clone(),
     });
 
     let mut server = HttpServer::new(move || {
@@ -43,6 +73,12 @@ async fn main() -> anyhow::Result<()> {
             .service(render_base64)
             .service(render_base64_multiple)
             .service(render_files)
+            .service(list_widgets)
+            .service(enable_widget)
+            .service(disable_widget)
+            .service(port_metrics)
+            .service(get_frames_json)
+            .service(get_frame)
             .app_data(state.clone())
     });
 
@@ -74,33 +110,206 @@ async fn main() -> anyhow::Result<()> {
     }
 
     let mut join_set: JoinSet<anyhow::Result<()>> = JoinSet::new();
-    let server = server.workers(config.num_http_workers).run();
-    join_set.spawn(async move {
-        server.await?;
-        Ok(())
-    });
+    // Signals are handled below, so the shutdown splash runs after the server has stopped
+    let server = server
+        .workers(config.num_http_workers)
+        .disable_signals()
+        .run();
+    let server_handle = server.handle();
+    join_set.spawn(async move { server.await.context("The HTTP server has failed") });
 
-    join_set.spawn(async move {
-        loop {
-            let render_task = receiver.recv().await?;
-            let start = Instant::now();
-
-            let config = config.clone();
-            match render_task.render(config).await {
-                Ok(_) => {
-                    debug!("Rendered task in {:?}", start.elapsed());
-                }
-                Err(err) => {
-                    error!(?err, "Failed to render task");
-                }
-            };
+    // Widgets and the state saver follow the configuration, so they restart on a reload
+    let mut widget_set: JoinSet<anyhow::Result<()>> = JoinSet::new();
+    spawn_config_tasks(&config, &sender, &mut widget_set);
+
+    let (stop_render, stop) = oneshot::channel();
+    join_set.spawn(render_loop(receiver.clone(), shared_config.clone(), stop));
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sighup = signal(SignalKind::hangup())?;
+    let mut exit_code = ExitCode::SUCCESS;
+    loop {
+        let result = tokio::select! {
+            _ = sigterm.recv() => {
+                info!("SIGTERM received, shutting down");
+                break;
+            }
+            _ = sigint.recv() => {
+                info!("SIGINT received, shutting down");
+                break;
+            }
+            _ = sighup.recv() => {
+                info!("SIGHUP received, reloading the configuration");
+                reload(&cmd_args, &shared_config, &sender, &mut widget_set).await;
+                continue;
+            }
+            Some(result) = join_set.join_next() => result,
+            Some(result) = widget_set.join_next() => result,
+        };
+        match result {
+            Ok(Ok(())) => error!("A task has stopped unexpectedly, shutting down"),
+            Ok(Err(err)) => error!(?err, "A task has failed, shutting down"),
+            Err(err) => error!(?err, "A task has panicked, shutting down"),
         }
-    });
+        exit_code = ExitCode::FAILURE;
+        break;
+    }
+
+    // Stop taking requests and stop the widgets, so nothing can replace the shutdown splash
+    server_handle.stop(true).await;
+    widget_set.shutdown().await;
+    let _ = stop_render.send(());
+    let config = shared_config.current();
 
-    if let Some(result) = join_set.join_next().await {
-        let result = result?;
-        warn!("Server stopped: {:?}", result);
+    let shutdown = shutdown(&config, &receiver, &mut join_set);
+    match tokio::time::timeout(config.shutdown_timeout, shutdown).await {
+        Ok(()) => info!("Shut down"),
+        Err(_) => {
+            error!(timeout = ?config.shutdown_timeout, "Shutdown did not finish in time");
+            exit_code = ExitCode::FAILURE;
+        }
     }
+    join_set.shutdown().await;
 
+    Ok(exit_code)
+}
+
+fn spawn_config_tasks(
+    config: &Arc<LedMatrixConfig>,
+    sender: &AsyncSender<RenderTask>,
+    join_set: &mut JoinSet<anyhow::Result<()>>,
+) {
+    spawn_widgets(config, sender, join_set);
+    if let Some(state) = config.state.clone() {
+        join_set.spawn(state.run(config.clone()));
+    }
+}
+
+/// Switches to the current content of the configuration file. The running configuration
+/// stays in place if the file is invalid.
+async fn reload(
+    cmd_args: &CmdArgs,
+    shared_config: &SharedConfig,
+    sender: &AsyncSender<RenderTask>,
+    widget_set: &mut JoinSet<anyhow::Result<()>>,
+) {
+    let current = shared_config.current();
+    let next = cmd_args
+        .overrides
+        .load(&cmd_args.config)
+        .and_then(|next| current.reload(next));
+    let next = match next {
+        Ok(next) => Arc::new(next),
+        Err(err) => {
+            error!(
+                ?err,
+                "Failed to reload the configuration, keeping the running one"
+            );
+            return;
+        }
+    };
+
+    if let Some(state) = current.state.as_ref() {
+        state.save(&current);
+    }
+    widget_set.shutdown().await;
+    // The replaced ports finish their queued frames and release their devices before the
+    // new ports, which may use the same devices, get any frames. A port stuck on a missing
+    // device must not keep the signals from being handled, though.
+    let replaced = current.replaced_ports(&next);
+    let close = join_all(replaced.iter().map(|port| async move {
+        if let Err(err) = port.close().await {
+            warn!(?err, matrix = %port.name(), "Failed to close the replaced port");
+        }
+    }));
+    if tokio::time::timeout(current.shutdown_timeout, close)
+        .await
+        .is_err()
+    {
+        warn!(
+            timeout = ?current.shutdown_timeout,
+            "Replaced ports did not close in time, switching anyway"
+        );
+    }
+    shared_config.replace(next.clone());
+    spawn_config_tasks(&next, sender, widget_set);
+    info!("Reloaded the configuration");
+}
+
+/// Hands the queued render tasks to the ports until `stop` fires, then waits for the frames
+/// it has handed over. Each port has its own lane in front of its queue, so the loop never
+/// waits for a port.
+async fn render_loop(
+    receiver: AsyncReceiver<RenderTask>,
+    config: Arc<SharedConfig>,
+    mut stop: oneshot::Receiver<()>,
+) -> anyhow::Result<()> {
+    let mut lanes = PortLanes::default();
+    let mut renders = JoinSet::new();
+    loop {
+        tokio::select! {
+            _ = &mut stop => break,
+            Some(_) = renders.join_next(), if !renders.is_empty() => {}
+            render_task = receiver.recv() => {
+                let render_task = render_task.context("The render queue is closed")?;
+                let start = Instant::now();
+                let render = render_task.submit(&config.current(), &mut lanes);
+                renders.spawn(async move { log_render_results(render.await, start) });
+            }
+        }
+    }
+
+    while renders.join_next().await.is_some() {}
+    lanes.finish().await;
     Ok(())
 }
+
+fn log_render_results(results: Vec<(MatrixName, anyhow::Result<()>)>, start: Instant) {
+    for (matrix, result) in results {
+        match result {
+            Ok(_) => {
+                debug!(%matrix, "Rendered task in {:?}", start.elapsed());
+            }
+            Err(err) => {
+                error!(?err, %matrix, "Failed to render task");
+            }
+        }
+    }
+}
+
+/// Waits for the stopped server and render loop, which finishes the frames in flight, then
+/// renders or drops the queued tasks, shows the shutdown splash and closes the ports.
+async fn shutdown(
+    config: &LedMatrixConfig,
+    receiver: &AsyncReceiver<RenderTask>,
+    tasks: &mut JoinSet<anyhow::Result<()>>,
+) {
+    while let Some(result) = tasks.join_next().await {
+        if let Ok(Err(err)) = result {
+            warn!(?err, "A task has failed while shutting down");
+        }
+    }
+
+    if config.flush_queue_on_shutdown {
+        let mut lanes = PortLanes::default();
+        while let Ok(Some(render_task)) = receiver.try_recv() {
+            log_render_results(render_task.submit(config, &mut lanes).await, Instant::now());
+        }
+        lanes.finish().await;
+    }
+    let dropped = receiver.len();
+    receiver.close();
+    if dropped > 0 {
+        info!(dropped, "Dropped queued render tasks");
+    }
+    // Before the splash, which is not worth restoring
+    if let Some(state) = config.state.as_ref() {
+        state.save(config);
+    }
+
+    if let Some(shutdown) = config.shutdown_splash.as_ref() {
+        config.show_splash(shutdown).await;
+    }
+    config.close_ports().await;
+}
@@ -0,0 +1,219 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::bail;
+use image::{GrayImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::led_matrix_config::LedMatrixConfig;
+use crate::config::matrix_dto::MatrixName;
+use crate::config::state_dto::StateDto;
+use crate::gfx::transition::Transition;
+
+/// Whether each widget is enabled, by widget name
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+struct WidgetStates {
+    widgets: BTreeMap<String, bool>,
+}
+
+/// Keeps the last displayed frame of each matrix and the widget states in a directory, so
+/// that a restarted daemon shows what it showed before.
+#[derive(Debug)]
+pub struct StateStore {
+    dir: PathBuf,
+    save_interval: Duration,
+}
+
+impl TryFrom<StateDto> for StateStore {
+    type Error = anyhow::Error;
+
+    fn try_from(value: StateDto) -> Result<Self, Self::Error> {
+        if value.save_interval.is_zero() {
+            bail!("State save_interval must be above zero");
+        }
+        Ok(StateStore {
+            dir: value.dir,
+            save_interval: value.save_interval,
+        })
+    }
+}
+
+impl StateStore {
+    fn frame_path(&self, matrix: &MatrixName) -> PathBuf {
+        self.dir.join(format!("{matrix}.png"))
+    }
+
+    fn widgets_path(&self) -> PathBuf {
+        self.dir.join("widgets.json")
+    }
+
+    /// Writes next to the target and renames, so a crash never leaves a truncated file.
+    fn write(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, content)?;
+        std::fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    fn save_frame(&self, matrix: &MatrixName, frame: &GrayImage) -> anyhow::Result<()> {
+        let mut png = std::io::Cursor::new(vec![]);
+        frame.write_to(&mut png, ImageFormat::Png)?;
+        Self::write(&self.frame_path(matrix), png.get_ref())
+    }
+
+    fn load_frame(&self, matrix: &MatrixName) -> anyhow::Result<Option<GrayImage>> {
+        let path = self.frame_path(matrix);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(image::open(path)?.to_luma8()))
+    }
+
+    fn save_widgets(&self, states: &WidgetStates) -> anyhow::Result<()> {
+        Self::write(&self.widgets_path(), &serde_json::to_vec_pretty(states)?)
+    }
+
+    fn load_widgets(&self) -> anyhow::Result<WidgetStates> {
+        let path = self.widgets_path();
+        if !path.exists() {
+            return Ok(WidgetStates::default());
+        }
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    fn widget_states(config: &LedMatrixConfig) -> WidgetStates {
+        WidgetStates {
+            widgets: config
+                .widgets
+                .iter()
+                .map(|widget| (widget.name.clone(), widget.is_enabled()))
+                .collect(),
+        }
+    }
+
+    /// Shows the persisted frames and applies the persisted widget states. Widgets that no
+    /// longer exist in the configuration are ignored, and a matrix that fails does not keep
+    /// the others from being restored.
+    pub async fn restore(&self, config: &LedMatrixConfig) -> anyhow::Result<()> {
+        for (name, enabled) in self.load_widgets()?.widgets {
+            if let Some(widget) = config.widget(&name) {
+                widget.set_enabled(enabled);
+            }
+        }
+
+        for port in config.matrices.iter() {
+            let restore = async {
+                if let Some(frame) = self.load_frame(port.name())? {
+                    port.submit_waiting(frame, Transition::default())
+                        .await?
+                        .await?;
+                    info!(matrix = %port.name(), "Restored the last frame");
+                }
+                anyhow::Ok(())
+            };
+            if let Err(err) = restore.await {
+                warn!(?err, matrix = %port.name(), "Failed to restore the last frame");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Saves the current frames and widget states right away, so nothing changed since the
+    /// last periodic save is lost when the daemon stops or reloads.
+    pub fn save(&self, config: &LedMatrixConfig) {
+        let result = std::fs::create_dir_all(&self.dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| {
+                self.save_changes(config, &mut HashMap::new(), &mut WidgetStates::default())
+            });
+        if let Err(err) = result {
+            warn!(?err, dir = ?self.dir, "Failed to save the state");
+        }
+    }
+
+    /// Saves whatever changed since the previous save.
+    fn save_changes(
+        &self,
+        config: &LedMatrixConfig,
+        saved_frames: &mut HashMap<MatrixName, GrayImage>,
+        saved_widgets: &mut WidgetStates,
+    ) -> anyhow::Result<()> {
+        for port in config.matrices.iter() {
+            if let Some(frame) = port.frame() {
+                if saved_frames.get(port.name()) != Some(&frame) {
+                    self.save_frame(port.name(), &frame)?;
+                    saved_frames.insert(port.name().clone(), frame);
+                }
+            }
+        }
+
+        let widgets = Self::widget_states(config);
+        if &widgets != saved_widgets {
+            self.save_widgets(&widgets)?;
+            *saved_widgets = widgets;
+        }
+
+        Ok(())
+    }
+
+    pub async fn run(self: Arc<Self>, config: Arc<LedMatrixConfig>) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        let mut saved_frames = HashMap::new();
+        let mut saved_widgets = self.load_widgets().unwrap_or_default();
+        let mut interval = tokio::time::interval(self.save_interval);
+
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.save_changes(&config, &mut saved_frames, &mut saved_widgets) {
+                warn!(?err, dir = ?self.dir, "Failed to save the state");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+
+    #[test]
+    fn test_zero_save_interval() {
+        let dto = StateDto {
+            dir: std::env::temp_dir(),
+            save_interval: Duration::ZERO,
+        };
+        assert!(StateStore::try_from(dto).is_err());
+    }
+
+    #[test]
+    fn test_roundtrip() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("led_matrix_state_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let store = StateStore {
+            dir: dir.clone(),
+            save_interval: Duration::from_secs(1),
+        };
+
+        let (left, desk) = (MatrixName::left(), MatrixName::from("desk"));
+        assert_eq!(store.load_frame(&left)?, None);
+        let frame = GrayImage::from_fn(9, 34, |x, y| Luma([(x * y) as u8]));
+        store.save_frame(&left, &frame)?;
+        assert_eq!(store.load_frame(&left)?, Some(frame));
+        assert_eq!(store.load_frame(&desk)?, None);
+
+        let states = WidgetStates {
+            widgets: BTreeMap::from([("clock".to_string(), false)]),
+        };
+        store.save_widgets(&states)?;
+        assert_eq!(store.load_widgets()?, states);
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
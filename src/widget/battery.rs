@@ -0,0 +1,154 @@
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use image::GrayImage;
+
+use crate::config::widget_dto::BatteryWidgetDto;
+use crate::gfx::font::draw_number;
+use crate::gfx::{blank_frame, set_pixel, DIM, FULL};
+use crate::widget::sysfs::read_power_supplies;
+use crate::widget::Widget;
+
+const BODY_TOP: i32 = 2;
+const BODY_BOTTOM: i32 = 25;
+const FILL_TOP: i32 = BODY_TOP + 2;
+const FILL_BOTTOM: i32 = BODY_BOTTOM - 2;
+const FILL_ROWS: i32 = FILL_BOTTOM - FILL_TOP + 1;
+const DIGITS_TOP: i32 = 28;
+
+/// A battery icon filled to the current capacity with the percentage below it.
+/// The fill climbs to the top while charging, and the icon blinks when the charge is low.
+#[derive(Debug)]
+pub struct BatteryWidget {
+    sysfs_root: PathBuf,
+    battery: Option<String>,
+    warning_threshold: u8,
+    tick: u32,
+}
+
+impl From<BatteryWidgetDto> for BatteryWidget {
+    fn from(value: BatteryWidgetDto) -> Self {
+        BatteryWidget {
+            sysfs_root: value.sysfs_root,
+            battery: value.battery,
+            warning_threshold: value.warning_threshold,
+            tick: 0,
+        }
+    }
+}
+
+impl BatteryWidget {
+    fn draw(&self, capacity: u8, charging: bool) -> GrayImage {
+        let mut img = blank_frame();
+
+        let blink_off = !charging && capacity <= self.warning_threshold && self.tick % 2 == 1;
+        if !blink_off {
+            // terminal
+            for x in 3..6 {
+                set_pixel(&mut img, x, 0, FULL);
+                set_pixel(&mut img, x, 1, FULL);
+            }
+            // outline
+            for y in BODY_TOP..=BODY_BOTTOM {
+                set_pixel(&mut img, 0, y, FULL);
+                set_pixel(&mut img, 8, y, FULL);
+            }
+            for x in 0..9 {
+                set_pixel(&mut img, x, BODY_TOP, FULL);
+                set_pixel(&mut img, x, BODY_BOTTOM, FULL);
+            }
+
+            let level = (capacity as i32 * FILL_ROWS + 50) / 100;
+            let animated = if charging {
+                self.tick as i32 % (FILL_ROWS - level + 1)
+            } else {
+                0
+            };
+            for row in 0..level + animated {
+                let brightness = if row < level { FULL } else { DIM };
+                for x in 2..7 {
+                    set_pixel(&mut img, x, FILL_BOTTOM - row, brightness);
+                }
+            }
+        }
+
+        draw_number(&mut img, DIGITS_TOP, capacity as u32, FULL);
+
+        img
+    }
+}
+
+impl Widget for BatteryWidget {
+    fn render(&mut self) -> anyhow::Result<GrayImage> {
+        let mut info = read_power_supplies(&self.sysfs_root)?;
+        if let Some(name) = self.battery.as_ref() {
+            info.batteries.retain(|battery| &battery.name == name);
+        }
+        let capacity = info
+            .capacity()
+            .ok_or_else(|| anyhow!("No batteries found in {:?}", self.sysfs_root))?;
+
+        let img = self.draw(capacity, info.charging());
+        self.tick = self.tick.wrapping_add(1);
+        Ok(img)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GenericImageView, Luma};
+
+    use super::*;
+
+    fn widget() -> BatteryWidget {
+        BatteryWidget::from(BatteryWidgetDto {
+            sysfs_root: PathBuf::from("test_data/sysfs"),
+            battery: None,
+            warning_threshold: 15,
+        })
+    }
+
+    fn fill_rows(img: &GrayImage, brightness: u8) -> i32 {
+        (FILL_TOP..=FILL_BOTTOM)
+            .filter(|&y| img.get_pixel(4, y as u32) == &Luma([brightness]))
+            .count() as i32
+    }
+
+    #[test]
+    fn test_render_charging() -> anyhow::Result<()> {
+        let mut widget = widget();
+
+        // 42% of 20 rows
+        let img = widget.render()?;
+        assert_eq!(fill_rows(&img, FULL), 8);
+        assert_eq!(fill_rows(&img, DIM), 0);
+
+        let img = widget.render()?;
+        assert_eq!(fill_rows(&img, FULL), 8);
+        assert_eq!(fill_rows(&img, DIM), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_low_battery_blinks() {
+        let mut widget = widget();
+        let on = widget.draw(10, false);
+        widget.tick += 1;
+        let off = widget.draw(10, false);
+
+        assert_eq!(fill_rows(&on, FULL), 2);
+        assert_eq!(off.get_pixel(0, BODY_TOP as u32), &Luma([0]));
+        // the percentage stays visible
+        assert_eq!(
+            on.view(0, DIGITS_TOP as u32, 9, 5).to_image(),
+            off.view(0, DIGITS_TOP as u32, 9, 5).to_image()
+        );
+    }
+
+    #[test]
+    fn test_full() {
+        let img = widget().draw(100, false);
+        assert_eq!(fill_rows(&img, FULL), FILL_ROWS);
+        assert_eq!(img.get_pixel(0, DIGITS_TOP as u32), &Luma([FULL]));
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::{Local, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use image::GrayImage;
+
+use crate::config::widget_dto::{ClockWidgetDto, HourFormat};
+use crate::gfx::font::draw_text;
+use crate::gfx::{blank_frame, set_pixel, DIM, FULL};
+use crate::widget::Widget;
+
+/// Renders HH:MM vertically on a single module: hours on top, a horizontal colon, minutes,
+/// and optionally seconds and an AM/PM marker below.
+#[derive(Debug)]
+pub struct ClockWidget {
+    show_seconds: bool,
+    hour_format: HourFormat,
+    blink_colon: bool,
+    timezone: Option<Tz>,
+}
+
+impl TryFrom<ClockWidgetDto> for ClockWidget {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ClockWidgetDto) -> Result<Self, Self::Error> {
+        let timezone = match value.timezone {
+            Some(timezone) => Some(
+                timezone
+                    .parse::<Tz>()
+                    .map_err(|err| anyhow::anyhow!("Invalid timezone {timezone}: {err}"))?,
+            ),
+            None => None,
+        };
+
+        Ok(ClockWidget {
+            show_seconds: value.show_seconds,
+            hour_format: value.hour_format,
+            blink_colon: value.blink_colon,
+            timezone,
+        })
+    }
+}
+
+impl ClockWidget {
+    fn now(&self) -> NaiveTime {
+        match self.timezone {
+            Some(timezone) => Utc::now().with_timezone(&timezone).time(),
+            None => Local::now().time(),
+        }
+    }
+
+    fn draw(&self, time: NaiveTime) -> GrayImage {
+        let mut img = blank_frame();
+
+        let (is_pm, hour) = match self.hour_format {
+            HourFormat::H24 => (false, time.hour()),
+            HourFormat::H12 => time.hour12(),
+        };
+
+        draw_text(&mut img, 1, 1, &format!("{hour:02}"), FULL);
+        if !self.blink_colon || time.second() % 2 == 0 {
+            set_pixel(&mut img, 3, 8, FULL);
+            set_pixel(&mut img, 5, 8, FULL);
+        }
+        draw_text(&mut img, 1, 11, &format!("{:02}", time.minute()), FULL);
+
+        if self.show_seconds {
+            draw_text(&mut img, 1, 19, &format!("{:02}", time.second()), DIM);
+        }
+
+        if self.hour_format == HourFormat::H12 {
+            draw_text(&mut img, 3, 28, if is_pm { "P" } else { "A" }, DIM);
+        }
+
+        img
+    }
+}
+
+impl Widget for ClockWidget {
+    fn render(&mut self) -> anyhow::Result<GrayImage> {
+        Ok(self.draw(self.now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+
+    fn clock(hour_format: HourFormat) -> ClockWidget {
+        ClockWidget::try_from(ClockWidgetDto {
+            show_seconds: true,
+            hour_format,
+            blink_colon: true,
+            timezone: Some("UTC".to_string()),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_draw() {
+        let time = NaiveTime::from_hms_opt(13, 7, 42).unwrap();
+        let img = clock(HourFormat::H24).draw(time);
+
+        let mut expected = blank_frame();
+        draw_text(&mut expected, 1, 1, "13", FULL);
+        draw_text(&mut expected, 1, 11, "07", FULL);
+        draw_text(&mut expected, 1, 19, "42", DIM);
+        set_pixel(&mut expected, 3, 8, FULL);
+        set_pixel(&mut expected, 5, 8, FULL);
+        assert_eq!(img, expected);
+    }
+
+    #[test]
+    fn test_blink_and_12h() {
+        let time = NaiveTime::from_hms_opt(13, 7, 43).unwrap();
+        let img = clock(HourFormat::H12).draw(time);
+
+        let mut hours = blank_frame();
+        draw_text(&mut hours, 1, 1, "01", FULL);
+        assert_eq!(img.get_pixel(3, 8), &Luma([0]));
+        assert!(hours
+            .enumerate_pixels()
+            .filter(|(_, _, &Luma([p]))| p > 0)
+            .all(|(x, y, _)| img.get_pixel(x, y) == &Luma([FULL])));
+    }
+
+    #[test]
+    fn test_invalid_timezone() {
+        assert!(ClockWidget::try_from(ClockWidgetDto {
+            show_seconds: false,
+            hour_format: HourFormat::H24,
+            blink_colon: false,
+            timezone: Some("Mars/Olympus_Mons".to_string()),
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context};
+use base64::Engine;
+use image::GrayImage;
+
+use crate::api::decode::decode_gray_image;
+use crate::config::widget_dto::{CommandOutput, CommandWidgetDto};
+use crate::gfx::font::text_frame;
+use crate::gfx::graph::draw_columns;
+use crate::gfx::{blank_frame, FULL};
+use crate::hw::{HEIGHT, WIDTH};
+use crate::widget::Widget;
+
+/// Runs a command on every tick and renders its stdout.
+#[derive(Debug)]
+pub struct CommandWidget {
+    command: Vec<String>,
+    env: BTreeMap<String, String>,
+    timeout: Duration,
+    max_output: usize,
+    output: CommandOutput,
+    min: f64,
+    max: f64,
+}
+
+impl TryFrom<CommandWidgetDto> for CommandWidget {
+    type Error = anyhow::Error;
+
+    fn try_from(value: CommandWidgetDto) -> Result<Self, Self::Error> {
+        if value.command.is_empty() {
+            bail!("Command must not be empty");
+        }
+        if value.max <= value.min {
+            bail!(
+                "Command max {} must be greater than min {}",
+                value.max,
+                value.min
+            );
+        }
+
+        Ok(CommandWidget {
+            command: value.command,
+            env: value.env,
+            timeout: value.timeout,
+            max_output: value.max_output,
+            output: value.output,
+            min: value.min,
+            max: value.max,
+        })
+    }
+}
+
+impl CommandWidget {
+    fn run(&self) -> anyhow::Result<Vec<u8>> {
+        // In its own process group, so background processes it starts can be killed with it
+        let mut child = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .envs(&self.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .with_context(|| format!("Failed to spawn {:?}", self.command[0]))?;
+
+        // Read in a separate thread, so a chatty command can't block on a full pipe
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Failed to capture stdout"))?;
+        let limit = self.max_output as u64 + 1;
+        let reader = std::thread::spawn(move || {
+            let mut output = vec![];
+            stdout.take(limit).read_to_end(&mut output).map(|_| output)
+        });
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break Some(status);
+            }
+            if Instant::now() >= deadline {
+                break None;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        // A background process may keep stdout open after the command has exited
+        while !reader.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let timed_out = status.is_none() || !reader.is_finished();
+        if timed_out {
+            // Closes stdout in every process of the group, which ends the reader
+            unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) };
+            child.wait()?;
+        }
+        let output = reader
+            .join()
+            .map_err(|_| anyhow!("Output reader panicked"))??;
+
+        // A command is killed by SIGPIPE once its output is cut off, so the size is checked
+        // before the status
+        if output.len() > self.max_output {
+            bail!("Command output exceeds {} bytes", self.max_output);
+        }
+        match status {
+            Some(status) if !timed_out => {
+                if !status.success() {
+                    bail!("Command failed: {status}");
+                }
+                Ok(output)
+            }
+            _ => bail!("Command timed out after {:?}", self.timeout),
+        }
+    }
+
+    fn parse_image(output: &str) -> anyhow::Result<GrayImage> {
+        let path = Path::new(output);
+        let buf = if path.is_file() {
+            std::fs::read(path)?
+        } else {
+            base64::engine::general_purpose::STANDARD.decode(output)?
+        };
+
+        let image = decode_gray_image(&buf, None)?;
+        if image.dimensions() != (WIDTH as u32, HEIGHT as u32) {
+            bail!(
+                "Image must be {WIDTH}x{HEIGHT} pixels; got {}x{}",
+                image.width(),
+                image.height()
+            );
+        }
+        Ok(image)
+    }
+
+    fn draw_number(&self, value: f64) -> GrayImage {
+        let mut img = blank_frame();
+        draw_columns(
+            &mut img,
+            &[((value - self.min) / (self.max - self.min)) as f32],
+        );
+        img
+    }
+
+    /// Draws the first line of the text.
+    fn draw_text(text: &str) -> GrayImage {
+        text_frame(text.lines().next().unwrap_or_default(), FULL)
+    }
+
+    fn interpret(&self, output: &[u8]) -> anyhow::Result<GrayImage> {
+        let output = String::from_utf8_lossy(output);
+        let output = output.trim();
+
+        match self.output {
+            CommandOutput::Number => Ok(self.draw_number(
+                output
+                    .parse()
+                    .with_context(|| format!("Not a number: {output:?}"))?,
+            )),
+            CommandOutput::Text => Ok(Self::draw_text(output)),
+            CommandOutput::Image => Self::parse_image(output),
+            CommandOutput::Auto => {
+                if let Ok(value) = output.parse() {
+                    return Ok(self.draw_number(value));
+                }
+                if let Ok(image) = Self::parse_image(output) {
+                    return Ok(image);
+                }
+                Ok(Self::draw_text(output))
+            }
+        }
+    }
+}
+
+impl Widget for CommandWidget {
+    fn render(&mut self) -> anyhow::Result<GrayImage> {
+        let output = self.run()?;
+        self.interpret(&output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+
+    fn widget(command: &[&str], output: CommandOutput) -> CommandWidget {
+        CommandWidget::try_from(CommandWidgetDto {
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+            env: BTreeMap::from([("LED_TEST".to_string(), "HI".to_string())]),
+            timeout: Duration::from_millis(500),
+            max_output: 10,
+            output,
+            min: 0.0,
+            max: 100.0,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_number() -> anyhow::Result<()> {
+        let img = widget(&["echo", "50"], CommandOutput::Auto).render()?;
+        assert_eq!(img.get_pixel(4, 33), &Luma([FULL]));
+        assert_eq!(img.get_pixel(4, 17), &Luma([FULL]));
+        assert_eq!(img.get_pixel(4, 16), &Luma([0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_with_env() -> anyhow::Result<()> {
+        let img = widget(&["sh", "-c", "echo $LED_TEST!"], CommandOutput::Auto).render()?;
+        assert_eq!(img, text_frame("HI!", FULL));
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_path() -> anyhow::Result<()> {
+        let mut widget = widget(&["echo", "test_data/img0.png"], CommandOutput::Image);
+        widget.max_output = 64;
+        let img = widget.render()?;
+        assert_eq!(img, image::open("test_data/img0.png")?.into_luma8());
+        Ok(())
+    }
+
+    #[test]
+    fn test_failures() {
+        assert!(widget(&["false"], CommandOutput::Auto).render().is_err());
+        assert!(widget(&["sleep", "5"], CommandOutput::Auto)
+            .render()
+            .is_err());
+        assert!(widget(&["echo", "12345678901"], CommandOutput::Text)
+            .render()
+            .is_err());
+        assert!(widget(&["echo", "abc"], CommandOutput::Number)
+            .render()
+            .is_err());
+    }
+
+    #[test]
+    fn test_background_process_is_killed() {
+        let start = Instant::now();
+        let err = widget(&["sh", "-c", "sleep 100 & echo 1"], CommandOutput::Auto)
+            .render()
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_output_limit_before_status() {
+        let err = widget(&["yes"], CommandOutput::Text).render().unwrap_err();
+        assert_eq!(err.to_string(), "Command output exceeds 10 bytes");
+    }
+}
@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+
+use image::GrayImage;
+
+use crate::config::widget_dto::CpuWidgetDto;
+use crate::gfx::graph::Graph;
+use crate::widget::procfs::{read_cpu_times, CpuTimes};
+use crate::widget::Widget;
+
+/// CPU utilization from `/proc/stat`, either aggregated or one bar or sparkline per core.
+#[derive(Debug)]
+pub struct CpuWidget {
+    procfs_root: PathBuf,
+    per_core: bool,
+    graph: Graph,
+    previous: Option<Vec<CpuTimes>>,
+}
+
+impl From<CpuWidgetDto> for CpuWidget {
+    fn from(value: CpuWidgetDto) -> Self {
+        CpuWidget {
+            procfs_root: value.procfs_root,
+            per_core: value.per_core,
+            graph: Graph::new(value.style),
+            previous: None,
+        }
+    }
+}
+
+impl Widget for CpuWidget {
+    fn render(&mut self) -> anyhow::Result<GrayImage> {
+        let times = read_cpu_times(&self.procfs_root)?;
+        let utilization: Vec<f32> = times
+            .iter()
+            .enumerate()
+            .map(|(index, current)| {
+                let previous = self.previous.as_ref().and_then(|times| times.get(index));
+                current.utilization(previous)
+            })
+            .collect();
+        self.previous = Some(times);
+
+        if self.per_core && utilization.len() > 1 {
+            Ok(self.graph.draw_each(&utilization[1..]))
+        } else {
+            Ok(self.graph.draw(&utilization[..1]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+    use crate::gfx::graph::GraphStyle;
+    use crate::gfx::FULL;
+
+    #[test]
+    fn test_render_per_core() -> anyhow::Result<()> {
+        let mut widget = CpuWidget::from(CpuWidgetDto {
+            procfs_root: PathBuf::from("test_data/procfs"),
+            per_core: true,
+            style: GraphStyle::Bars,
+        });
+        let img = widget.render()?;
+
+        // cpu0 is 87.5% busy, cpu1 is 25% busy
+        assert_eq!(img.get_pixel(6, 0), &Luma([FULL]));
+        assert_eq!(img.get_pixel(7, 0), &Luma([223]));
+        assert_eq!(img.get_pixel(8, 0), &Luma([0]));
+        assert_eq!(img.get_pixel(1, 17), &Luma([FULL]));
+        assert_eq!(img.get_pixel(2, 17), &Luma([64]));
+        assert_eq!(img.get_pixel(3, 17), &Luma([0]));
+
+        // no time passed between the samples
+        let img = widget.render()?;
+        assert!(img.pixels().all(|&Luma([p])| p == 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_core_sparkline() -> anyhow::Result<()> {
+        let mut widget = CpuWidget::from(CpuWidgetDto {
+            procfs_root: PathBuf::from("test_data/procfs"),
+            per_core: true,
+            style: GraphStyle::Sparkline,
+        });
+        let img = widget.render()?;
+
+        // one strip of 4 columns per core: cpu0 at 87.5%, cpu1 at 25%
+        assert_eq!(img.get_pixel(2, 33), &Luma([FULL]));
+        assert_eq!(img.get_pixel(3, 33), &Luma([128]));
+        assert_eq!(img.get_pixel(4, 33), &Luma([0]));
+        assert_eq!(img.get_pixel(5, 33), &Luma([FULL]));
+        assert_eq!(img.get_pixel(6, 33), &Luma([0]));
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use image::GrayImage;
+
+use crate::config::widget_dto::LoadWidgetDto;
+use crate::gfx::graph::Graph;
+use crate::widget::procfs::{read_cpu_times, read_loadavg};
+use crate::widget::Widget;
+
+/// The 1, 5 and 15 minute load averages from `/proc/loadavg`, relative to the number of cores.
+#[derive(Debug)]
+pub struct LoadWidget {
+    procfs_root: PathBuf,
+    graph: Graph,
+}
+
+impl From<LoadWidgetDto> for LoadWidget {
+    fn from(value: LoadWidgetDto) -> Self {
+        LoadWidget {
+            procfs_root: value.procfs_root,
+            graph: Graph::new(value.style),
+        }
+    }
+}
+
+impl Widget for LoadWidget {
+    fn render(&mut self) -> anyhow::Result<GrayImage> {
+        let cores = (read_cpu_times(&self.procfs_root)?.len() - 1).max(1) as f32;
+        let values = read_loadavg(&self.procfs_root)?.map(|load| (load / cores).min(1.0));
+        Ok(self.graph.draw(&values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+    use crate::gfx::graph::GraphStyle;
+    use crate::gfx::FULL;
+
+    fn widget(style: GraphStyle) -> LoadWidget {
+        LoadWidget::from(LoadWidgetDto {
+            procfs_root: PathBuf::from("test_data/procfs"),
+            style,
+        })
+    }
+
+    #[test]
+    fn test_render_bars() -> anyhow::Result<()> {
+        let img = widget(GraphStyle::Bars).render()?;
+
+        // 1.0, 2.0 and 0.5 on two cores, in three 10-row bars
+        assert_eq!(img.get_pixel(3, 0), &Luma([FULL]));
+        assert_eq!(img.get_pixel(4, 0), &Luma([128]));
+        assert_eq!(img.get_pixel(5, 0), &Luma([0]));
+        assert_eq!(img.get_pixel(0, 10), &Luma([0]));
+        assert_eq!(img.get_pixel(8, 11), &Luma([FULL]));
+        assert_eq!(img.get_pixel(1, 22), &Luma([FULL]));
+        assert_eq!(img.get_pixel(2, 22), &Luma([64]));
+        assert_eq!(img.get_pixel(3, 22), &Luma([0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_sparkline() -> anyhow::Result<()> {
+        let img = widget(GraphStyle::Sparkline).render()?;
+
+        // the mean of 0.5, 1.0 and 0.25 is 5.25 of 9 pixels
+        assert_eq!(img.get_pixel(4, 33), &Luma([FULL]));
+        assert_eq!(img.get_pixel(5, 33), &Luma([64]));
+        assert!((0..9).all(|x| img.get_pixel(x, 32) == &Luma([0])));
+        Ok(())
+    }
+}
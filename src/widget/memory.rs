@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use image::GrayImage;
+
+use crate::config::widget_dto::MemoryWidgetDto;
+use crate::gfx::graph::Graph;
+use crate::widget::procfs::read_meminfo;
+use crate::widget::Widget;
+
+/// Used memory and, if any swap is configured, used swap from `/proc/meminfo`.
+#[derive(Debug)]
+pub struct MemoryWidget {
+    procfs_root: PathBuf,
+    graph: Graph,
+}
+
+impl From<MemoryWidgetDto> for MemoryWidget {
+    fn from(value: MemoryWidgetDto) -> Self {
+        MemoryWidget {
+            procfs_root: value.procfs_root,
+            graph: Graph::new(value.style),
+        }
+    }
+}
+
+impl Widget for MemoryWidget {
+    fn render(&mut self) -> anyhow::Result<GrayImage> {
+        let info = read_meminfo(&self.procfs_root)?;
+        let mut values = vec![info.mem_used()];
+        if info.swap_total > 0 {
+            values.push(info.swap_used());
+        }
+        Ok(self.graph.draw(&values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+    use crate::gfx::graph::GraphStyle;
+    use crate::gfx::FULL;
+
+    fn widget(style: GraphStyle) -> MemoryWidget {
+        MemoryWidget::from(MemoryWidgetDto {
+            procfs_root: PathBuf::from("test_data/procfs"),
+            style,
+        })
+    }
+
+    #[test]
+    fn test_render_bars() -> anyhow::Result<()> {
+        let img = widget(GraphStyle::Bars).render()?;
+
+        // 75% of the memory and 25% of the swap are used
+        assert_eq!(img.get_pixel(5, 0), &Luma([FULL]));
+        assert_eq!(img.get_pixel(6, 0), &Luma([191]));
+        assert_eq!(img.get_pixel(7, 0), &Luma([0]));
+        assert_eq!(img.get_pixel(0, 16), &Luma([0]));
+        assert_eq!(img.get_pixel(1, 17), &Luma([FULL]));
+        assert_eq!(img.get_pixel(2, 17), &Luma([64]));
+        assert_eq!(img.get_pixel(3, 17), &Luma([0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_sparkline() -> anyhow::Result<()> {
+        let img = widget(GraphStyle::Sparkline).render()?;
+
+        // the mean of memory and swap
+        assert_eq!(img.get_pixel(3, 33), &Luma([FULL]));
+        assert_eq!(img.get_pixel(4, 33), &Luma([128]));
+        assert_eq!(img.get_pixel(5, 33), &Luma([0]));
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use image::GrayImage;
+use kanal::AsyncSender;
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
+
+use crate::api::render_task::{RenderTarget, RenderTask};
+use crate::config::led_matrix_config::LedMatrixConfig;
+use crate::config::matrix_dto::MatrixName;
+use crate::config::widget_dto::{WidgetDto, WidgetKindDto};
+use crate::gfx::error_frame;
+use crate::gfx::transition::Transition;
+use crate::widget::battery::BatteryWidget;
+use crate::widget::clock::ClockWidget;
+use crate::widget::command::CommandWidget;
+use crate::widget::cpu::CpuWidget;
+use crate::widget::load::LoadWidget;
+use crate::widget::memory::MemoryWidget;
+use crate::widget::temperature::TemperatureWidget;
+use crate::widget::throughput::ThroughputWidget;
+
+pub mod battery;
+pub mod clock;
+pub mod command;
+pub mod cpu;
+pub mod load;
+pub mod memory;
+mod procfs;
+pub mod scheduler;
+mod sysfs;
+pub mod temperature;
+pub mod throughput;
+
+/// A daemon-side frame source that produces a full 9x34 frame on every tick.
+pub trait Widget: Send + Debug {
+    fn render(&mut self) -> anyhow::Result<GrayImage>;
+}
+
+#[derive(Debug)]
+pub struct WidgetHandle {
+    pub name: String,
+    pub kind: &'static str,
+    /// Standalone widgets render to their matrix; the others are only shown by a schedule.
+    pub matrix: Option<MatrixName>,
+    pub interval: Duration,
+    enabled: AtomicBool,
+    last_error: Mutex<Option<String>>,
+    widget: Arc<Mutex<Box<dyn Widget>>>,
+}
+
+impl TryFrom<WidgetDto> for WidgetHandle {
+    type Error = anyhow::Error;
+
+    fn try_from(value: WidgetDto) -> Result<Self, Self::Error> {
+        if value.interval.is_zero() {
+            bail!("Widget {} needs an interval above zero", value.name);
+        }
+        let (kind, widget): (&'static str, Box<dyn Widget>) = match value.kind {
+            WidgetKindDto::Clock(clock) => ("clock", Box::new(ClockWidget::try_from(clock)?)),
+            WidgetKindDto::Cpu(cpu) => ("cpu", Box::new(CpuWidget::from(cpu))),
+            WidgetKindDto::Memory(memory) => ("memory", Box::new(MemoryWidget::from(memory))),
+            WidgetKindDto::Load(load) => ("load", Box::new(LoadWidget::from(load))),
+            WidgetKindDto::Battery(battery) => ("battery", Box::new(BatteryWidget::from(battery))),
+            WidgetKindDto::Network(network) => {
+                ("network", Box::new(ThroughputWidget::from(network)))
+            }
+            WidgetKindDto::Disk(disk) => ("disk", Box::new(ThroughputWidget::from(disk))),
+            WidgetKindDto::Command(command) => {
+                ("command", Box::new(CommandWidget::try_from(command)?))
+            }
+            WidgetKindDto::Temperature(temperature) => (
+                "temperature",
+                Box::new(TemperatureWidget::try_from(temperature)?),
+            ),
+        };
+
+        Ok(WidgetHandle {
+            name: value.name,
+            kind,
+            matrix: value.port,
+            interval: value.interval,
+            enabled: AtomicBool::new(value.enabled),
+            last_error: Mutex::new(None),
+            widget: Arc::new(Mutex::new(widget)),
+        })
+    }
+}
+
+impl WidgetHandle {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().ok().and_then(|err| err.clone())
+    }
+
+    fn set_last_error(&self, error: Option<String>) {
+        if let Ok(mut last_error) = self.last_error.lock() {
+            *last_error = error;
+        }
+    }
+
+    async fn render(&self) -> anyhow::Result<GrayImage> {
+        let widget = self.widget.clone();
+        tokio::task::spawn_blocking(move || {
+            widget
+                .lock()
+                .map_err(|err| anyhow!("Poisoned mutex: {err:?}"))?
+                .render()
+        })
+        .await?
+    }
+
+    /// Renders a frame and queues it, unless an explicit render request holds the port.
+    /// Failures are recorded in the status and rendered as an error glyph.
+    async fn tick(
+        &self,
+        matrix: &MatrixName,
+        transition: Transition,
+        config: &LedMatrixConfig,
+        sender: &AsyncSender<RenderTask>,
+    ) -> anyhow::Result<()> {
+        if config.holds.is_held(matrix) {
+            return Ok(());
+        }
+
+        let image = match self.render().await {
+            Ok(image) => {
+                self.set_last_error(None);
+                image
+            }
+            Err(err) => {
+                warn!(?err, widget = %self.name, "Failed to render widget");
+                self.set_last_error(Some(format!("{err:#}")));
+                error_frame()
+            }
+        };
+
+        // A full queue means the frame would be stale by the time it is rendered anyway
+        let task = RenderTask {
+            target: RenderTarget::single(matrix.clone(), image),
+            transition,
+            droppable: true,
+        };
+        if !sender.try_send(task)? {
+            debug!(widget = %self.name, "Render queue is full, skipping widget frame");
+        }
+        Ok(())
+    }
+
+    async fn run(
+        self: Arc<Self>,
+        matrix: MatrixName,
+        config: Arc<LedMatrixConfig>,
+        sender: AsyncSender<RenderTask>,
+    ) -> anyhow::Result<()> {
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+            if self.is_enabled() {
+                self.tick(&matrix, Transition::default(), &config, &sender)
+                    .await?;
+            }
+        }
+    }
+}
+
+/// Spawns standalone widgets bound to a matrix, and a rotation for every matrix with a
+/// schedule.
+pub fn spawn_widgets(
+    config: &Arc<LedMatrixConfig>,
+    sender: &AsyncSender<RenderTask>,
+    join_set: &mut JoinSet<anyhow::Result<()>>,
+) {
+    for widget in config.widgets.iter() {
+        if let Some(matrix) = widget.matrix.clone() {
+            join_set.spawn(widget.clone().run(matrix, config.clone(), sender.clone()));
+        }
+    }
+
+    for schedule in config.schedules.iter() {
+        join_set.spawn(schedule.clone().run(config.clone(), sender.clone()));
+    }
+}
@@ -0,0 +1,256 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CpuTimes {
+    pub busy: u64,
+    pub total: u64,
+}
+
+impl CpuTimes {
+    /// Utilization between two samples, or since boot if there is no previous sample.
+    pub fn utilization(&self, previous: Option<&CpuTimes>) -> f32 {
+        let previous = previous.copied().unwrap_or_default();
+        let total = self.total.saturating_sub(previous.total);
+        if total == 0 {
+            return 0.0;
+        }
+        self.busy.saturating_sub(previous.busy) as f32 / total as f32
+    }
+}
+
+/// CPU times from `/proc/stat`: the aggregate `cpu` line first, followed by every `cpuN` line.
+pub fn read_cpu_times(procfs_root: &Path) -> anyhow::Result<Vec<CpuTimes>> {
+    let path = procfs_root.join("stat");
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+
+    let mut times = vec![];
+    for line in content.lines().filter(|line| line.starts_with("cpu")) {
+        let fields = line
+            .split_whitespace()
+            .skip(1)
+            .map(|field| field.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Invalid cpu line: {line}"))?;
+
+        // user nice system idle iowait irq softirq steal; guest times are already in user/nice
+        let total: u64 = fields.iter().take(8).sum();
+        let idle =
+            fields.get(3).copied().unwrap_or_default() + fields.get(4).copied().unwrap_or_default();
+        times.push(CpuTimes {
+            busy: total.saturating_sub(idle),
+            total,
+        });
+    }
+
+    if times.is_empty() {
+        return Err(anyhow!("No cpu lines in {path:?}"));
+    }
+    Ok(times)
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MemInfo {
+    pub mem_total: u64,
+    pub mem_available: u64,
+    pub swap_total: u64,
+    pub swap_free: u64,
+}
+
+impl MemInfo {
+    pub fn mem_used(&self) -> f32 {
+        used_fraction(self.mem_total, self.mem_available)
+    }
+
+    pub fn swap_used(&self) -> f32 {
+        used_fraction(self.swap_total, self.swap_free)
+    }
+}
+
+fn used_fraction(total: u64, free: u64) -> f32 {
+    if total == 0 {
+        return 0.0;
+    }
+    total.saturating_sub(free) as f32 / total as f32
+}
+
+pub fn read_meminfo(procfs_root: &Path) -> anyhow::Result<MemInfo> {
+    let path = procfs_root.join("meminfo");
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+
+    let mut info = MemInfo::default();
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let field = match key {
+            "MemTotal" => &mut info.mem_total,
+            "MemAvailable" => &mut info.mem_available,
+            "SwapTotal" => &mut info.swap_total,
+            "SwapFree" => &mut info.swap_free,
+            _ => continue,
+        };
+        *field = value
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid meminfo line: {line}"))?;
+    }
+
+    if info.mem_total == 0 {
+        return Err(anyhow!("No MemTotal in {path:?}"));
+    }
+    Ok(info)
+}
+
+/// The 1, 5 and 15 minute load averages from `/proc/loadavg`.
+pub fn read_loadavg(procfs_root: &Path) -> anyhow::Result<[f32; 3]> {
+    let path = procfs_root.join("loadavg");
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+
+    let mut fields = content.split_whitespace().map(|field| field.parse::<f32>());
+    let mut next = || -> anyhow::Result<f32> {
+        fields
+            .next()
+            .ok_or_else(|| anyhow!("Truncated {path:?}"))?
+            .with_context(|| format!("Invalid {path:?}"))
+    };
+    Ok([next()?, next()?, next()?])
+}
+
+/// Cumulative counters of a network interface or block device, in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IoCounters {
+    pub name: String,
+    /// Received bytes for interfaces, read bytes for block devices.
+    pub rx: u64,
+    /// Transmitted bytes for interfaces, written bytes for block devices.
+    pub tx: u64,
+}
+
+/// Per-interface counters from `/proc/net/dev`.
+pub fn read_net_dev(procfs_root: &Path) -> anyhow::Result<Vec<IoCounters>> {
+    let path = procfs_root.join("net").join("dev");
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+
+    // the first two lines are headers
+    let mut counters = vec![];
+    for line in content.lines().skip(2) {
+        let Some((name, fields)) = line.split_once(':') else {
+            continue;
+        };
+        let fields = fields
+            .split_whitespace()
+            .map(|field| field.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Invalid net/dev line: {line}"))?;
+        if fields.len() < 9 {
+            return Err(anyhow!("Truncated net/dev line: {line}"));
+        }
+        counters.push(IoCounters {
+            name: name.trim().to_string(),
+            rx: fields[0],
+            tx: fields[8],
+        });
+    }
+    Ok(counters)
+}
+
+/// Per-device counters from `/proc/diskstats`; sectors are always 512 bytes there.
+pub fn read_diskstats(procfs_root: &Path) -> anyhow::Result<Vec<IoCounters>> {
+    let path = procfs_root.join("diskstats");
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+
+    let mut counters = vec![];
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            continue;
+        }
+        let parse = |index: usize| {
+            fields[index]
+                .parse::<u64>()
+                .with_context(|| format!("Invalid diskstats line: {line}"))
+        };
+        counters.push(IoCounters {
+            name: fields[2].to_string(),
+            rx: parse(5)? * 512,
+            tx: parse(9)? * 512,
+        });
+    }
+    Ok(counters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "test_data/procfs";
+
+    #[test]
+    fn test_read_cpu_times() -> anyhow::Result<()> {
+        let times = read_cpu_times(Path::new(ROOT))?;
+        assert_eq!(times.len(), 3);
+        assert_eq!(times[0].utilization(None), 0.5);
+        assert_eq!(times[1].utilization(None), 0.875);
+        assert_eq!(times[2].utilization(None), 0.25);
+
+        let next = CpuTimes {
+            busy: times[0].busy + 30,
+            total: times[0].total + 100,
+        };
+        assert_eq!(next.utilization(Some(&times[0])), 0.3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_meminfo() -> anyhow::Result<()> {
+        let info = read_meminfo(Path::new(ROOT))?;
+        assert_eq!(info.mem_used(), 0.75);
+        assert_eq!(info.swap_used(), 0.25);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_net_dev() -> anyhow::Result<()> {
+        let counters = read_net_dev(Path::new(ROOT))?;
+        assert_eq!(counters.len(), 3);
+        assert_eq!(
+            counters[1],
+            IoCounters {
+                name: "wlp1s0".to_string(),
+                rx: 1000000,
+                tx: 250000,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_diskstats() -> anyhow::Result<()> {
+        let counters = read_diskstats(Path::new(ROOT))?;
+        assert_eq!(counters.len(), 9);
+        assert_eq!(
+            counters[1],
+            IoCounters {
+                name: "nvme0n1".to_string(),
+                rx: 2000 * 512,
+                tx: 1000 * 512,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_loadavg() -> anyhow::Result<()> {
+        assert_eq!(read_loadavg(Path::new(ROOT))?, [1.0, 2.0, 0.5]);
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use chrono::{Local, NaiveTime};
+use kanal::AsyncSender;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::debug;
+
+use crate::api::render_task::RenderTask;
+use crate::config::led_matrix_config::LedMatrixConfig;
+use crate::config::matrix_dto::MatrixName;
+use crate::config::schedule_dto::{PowerSource, ScreenDto};
+use crate::gfx::transition::Transition;
+use crate::widget::sysfs::read_power_supplies;
+use crate::widget::WidgetHandle;
+
+/// Tracks explicit render requests, which take precedence over widgets for a while.
+#[derive(Debug)]
+pub struct RenderHolds {
+    duration: Duration,
+    /// Shared with the holds of the configurations reloaded from this one
+    until: Arc<Mutex<HashMap<MatrixName, Instant>>>,
+}
+
+impl RenderHolds {
+    pub fn new(duration: Duration) -> Self {
+        RenderHolds {
+            duration,
+            until: Arc::default(),
+        }
+    }
+
+    /// The same holds for a reloaded configuration: running holds keep their end, new ones
+    /// last `duration`.
+    pub fn carry_over(&self, duration: Duration) -> Self {
+        RenderHolds {
+            duration,
+            until: self.until.clone(),
+        }
+    }
+
+    pub fn hold(&self, matrix: &MatrixName) {
+        if let Ok(mut until) = self.until.lock() {
+            until.insert(matrix.clone(), Instant::now() + self.duration);
+        }
+    }
+
+    pub fn is_held(&self, matrix: &MatrixName) -> bool {
+        self.until
+            .lock()
+            .map(|until| {
+                until
+                    .get(matrix)
+                    .is_some_and(|&until| Instant::now() < until)
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+pub struct Screen {
+    widget: Arc<WidgetHandle>,
+    duration: Duration,
+    /// In the system's local timezone, independent of any widget `timezone`
+    hours: Option<(NaiveTime, NaiveTime)>,
+    power: Option<PowerSource>,
+}
+
+fn parse_hours(hours: &str) -> anyhow::Result<(NaiveTime, NaiveTime)> {
+    let (from, to) = hours
+        .split_once('-')
+        .ok_or_else(|| anyhow!("Invalid hours {hours}, expected HH:MM-HH:MM"))?;
+    let parse = |time: &str| {
+        NaiveTime::parse_from_str(time.trim(), "%H:%M")
+            .with_context(|| format!("Invalid hours {hours}, expected HH:MM-HH:MM"))
+    };
+    Ok((parse(from)?, parse(to)?))
+}
+
+impl Screen {
+    pub fn try_from_dto(value: ScreenDto, widget: Arc<WidgetHandle>) -> anyhow::Result<Self> {
+        if value.duration.is_zero() {
+            bail!("Screen {} needs a duration above zero", value.widget);
+        }
+        // the schedule ticks the widget on its interval
+        if widget.interval.is_zero() {
+            bail!("Screen {} needs a widget interval above zero", value.widget);
+        }
+        Ok(Screen {
+            widget,
+            duration: value.duration,
+            hours: value.hours.as_deref().map(parse_hours).transpose()?,
+            power: value.power,
+        })
+    }
+
+    pub fn widget(&self) -> &Arc<WidgetHandle> {
+        &self.widget
+    }
+
+    fn is_active(&self, now: NaiveTime, sysfs_root: &Path) -> bool {
+        if !self.widget.is_enabled() {
+            return false;
+        }
+
+        if let Some((from, to)) = self.hours {
+            let within = if from <= to {
+                from <= now && now < to
+            } else {
+                now >= from || now < to
+            };
+            if !within {
+                return false;
+            }
+        }
+
+        if let Some(power) = self.power {
+            let ac_online = match read_power_supplies(sysfs_root) {
+                Ok(info) => info.ac_online,
+                Err(err) => {
+                    debug!(?err, "Failed to read power supplies");
+                    return false;
+                }
+            };
+            if ac_online != (power == PowerSource::Ac) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Rotates between screens on one matrix, skipping the ones whose conditions do not hold.
+#[derive(Debug)]
+pub struct Schedule {
+    pub matrix: MatrixName,
+    pub screens: Vec<Screen>,
+    pub sysfs_root: PathBuf,
+}
+
+impl Schedule {
+    fn next_screen(&self, start: usize) -> Option<usize> {
+        let now = Local::now().time();
+        (0..self.screens.len())
+            .map(|offset| (start + offset) % self.screens.len())
+            .find(|&index| self.screens[index].is_active(now, &self.sysfs_root))
+    }
+
+    pub async fn run(
+        self: Arc<Self>,
+        config: Arc<LedMatrixConfig>,
+        sender: AsyncSender<RenderTask>,
+    ) -> anyhow::Result<()> {
+        let mut start = 0;
+        loop {
+            let Some(index) = self.next_screen(start) else {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            };
+            start = index + 1;
+
+            let screen = &self.screens[index];
+            debug!(matrix = %self.matrix, widget = %screen.widget.name, "Switching screen");
+
+            let deadline = tokio::time::sleep(screen.duration);
+            tokio::pin!(deadline);
+            let mut interval = tokio::time::interval(screen.widget.interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            // only the first frame of a screen transitions from the previous screen
+            let mut transition = config.transition;
+            loop {
+                tokio::select! {
+                    _ = &mut deadline => break,
+                    _ = interval.tick() => {
+                        screen.widget.tick(&self.matrix, transition, &config, &sender).await?;
+                        transition = Transition::default();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::widget_dto::{ClockWidgetDto, HourFormat, WidgetDto, WidgetKindDto};
+
+    fn clock() -> WidgetDto {
+        WidgetDto {
+            name: "clock".to_string(),
+            port: None,
+            enabled: true,
+            interval: Duration::from_secs(1),
+            kind: WidgetKindDto::Clock(ClockWidgetDto {
+                show_seconds: false,
+                hour_format: HourFormat::H24,
+                blink_colon: false,
+                timezone: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_parse_hours() -> anyhow::Result<()> {
+        let (from, to) = parse_hours("22:30 - 06:00")?;
+        assert_eq!(from, NaiveTime::from_hms_opt(22, 30, 0).unwrap());
+        assert_eq!(to, NaiveTime::from_hms_opt(6, 0, 0).unwrap());
+        assert!(parse_hours("22:30").is_err());
+        assert!(parse_hours("25:00-06:00").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_screen_conditions() -> anyhow::Result<()> {
+        let widget = Arc::new(WidgetHandle::try_from(clock())?);
+        let screen = |hours: &str, power| {
+            Screen::try_from_dto(
+                ScreenDto {
+                    widget: "clock".to_string(),
+                    duration: Duration::from_secs(1),
+                    hours: Some(hours.to_string()),
+                    power,
+                },
+                widget.clone(),
+            )
+        };
+        let sysfs_root = Path::new("test_data/sysfs");
+        let at = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+
+        let night = screen("22:00-06:00", None)?;
+        assert!(night.is_active(at(23), sysfs_root));
+        assert!(night.is_active(at(5), sysfs_root));
+        assert!(!night.is_active(at(12), sysfs_root));
+
+        // the fixture is plugged in
+        assert!(screen("00:00-23:59", Some(PowerSource::Ac))?.is_active(at(12), sysfs_root));
+        assert!(!screen("00:00-23:59", Some(PowerSource::Battery))?.is_active(at(12), sysfs_root));
+
+        widget.set_enabled(false);
+        assert!(!night.is_active(at(23), sysfs_root));
+
+        let zero = ScreenDto {
+            widget: "clock".to_string(),
+            duration: Duration::ZERO,
+            hours: None,
+            power: None,
+        };
+        assert!(Screen::try_from_dto(zero, widget).is_err());
+
+        let mut ticking = WidgetHandle::try_from(clock())?;
+        ticking.interval = Duration::ZERO;
+        let screen = ScreenDto {
+            widget: "clock".to_string(),
+            duration: Duration::from_secs(1),
+            hours: None,
+            power: None,
+        };
+        assert!(Screen::try_from_dto(screen, Arc::new(ticking)).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_holds() {
+        let holds = RenderHolds::new(Duration::from_secs(60));
+        assert!(!holds.is_held(&MatrixName::left()));
+        holds.hold(&MatrixName::left());
+        assert!(holds.is_held(&MatrixName::left()));
+        assert!(!holds.is_held(&MatrixName::right()));
+
+        let reloaded = holds.carry_over(Duration::ZERO);
+        assert!(reloaded.is_held(&MatrixName::left()));
+        reloaded.hold(&MatrixName::right());
+        assert!(!holds.is_held(&MatrixName::right()));
+    }
+}
@@ -0,0 +1,220 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+/// Entries of a sysfs class directory, e.g. `class/power_supply`, sorted by name.
+fn class_entries(sysfs_root: &Path, class: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let dir = sysfs_root.join("class").join(class);
+    let mut entries = std::fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read {dir:?}"))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatteryInfo {
+    pub name: String,
+    pub capacity: u8,
+    pub charging: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PowerSupplyInfo {
+    pub batteries: Vec<BatteryInfo>,
+    pub ac_online: bool,
+}
+
+impl PowerSupplyInfo {
+    pub fn capacity(&self) -> Option<u8> {
+        if self.batteries.is_empty() {
+            return None;
+        }
+        let sum: u32 = self
+            .batteries
+            .iter()
+            .map(|battery| battery.capacity as u32)
+            .sum();
+        Some((sum / self.batteries.len() as u32) as u8)
+    }
+
+    pub fn charging(&self) -> bool {
+        self.batteries.iter().any(|battery| battery.charging)
+    }
+}
+
+pub fn read_power_supplies(sysfs_root: &Path) -> anyhow::Result<PowerSupplyInfo> {
+    let mut info = PowerSupplyInfo {
+        batteries: vec![],
+        ac_online: false,
+    };
+
+    for entry in class_entries(sysfs_root, "power_supply")? {
+        match read_trimmed(&entry.join("type")).as_deref() {
+            Some("Battery") => {
+                let Some(capacity) = read_trimmed(&entry.join("capacity")) else {
+                    continue;
+                };
+                info.batteries.push(BatteryInfo {
+                    name: entry
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    capacity: capacity
+                        .parse::<u8>()
+                        .with_context(|| format!("Invalid capacity in {entry:?}"))?
+                        .min(100),
+                    charging: read_trimmed(&entry.join("status")).as_deref() == Some("Charging"),
+                });
+            }
+            Some("Mains") => {
+                info.ac_online |= read_trimmed(&entry.join("online")).as_deref() == Some("1");
+            }
+            _ => {}
+        }
+    }
+
+    Ok(info)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SensorValue {
+    /// Degrees Celsius.
+    Temperature(f32),
+    Fan(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorReading {
+    /// `<hwmon name>/<label>` for hwmon sensors, e.g. `k10temp/Tctl` or `cros_ec/fan1`,
+    /// and `<zone type>/<zone>` for thermal zones, e.g. `acpitz/thermal_zone0`.
+    pub label: String,
+    pub value: SensorValue,
+}
+
+impl SensorReading {
+    /// Matches either the full label or just the part after the device name.
+    pub fn matches(&self, label: &str) -> bool {
+        self.label == label
+            || self
+                .label
+                .split_once('/')
+                .is_some_and(|(_, sensor)| sensor == label)
+    }
+}
+
+/// Temperatures and fan speeds from `class/hwmon` and `class/thermal`.
+pub fn read_sensors(sysfs_root: &Path) -> anyhow::Result<Vec<SensorReading>> {
+    let mut readings = vec![];
+
+    for entry in class_entries(sysfs_root, "hwmon")? {
+        let device = read_trimmed(&entry.join("name")).unwrap_or_default();
+        let mut inputs = std::fs::read_dir(&entry)
+            .with_context(|| format!("Failed to read {entry:?}"))?
+            .filter_map(|file| file.ok())
+            .filter_map(|file| {
+                let name = file.file_name().to_string_lossy().to_string();
+                name.strip_suffix("_input").map(str::to_string)
+            })
+            .filter(|sensor| sensor.starts_with("temp") || sensor.starts_with("fan"))
+            .collect::<Vec<_>>();
+        inputs.sort();
+
+        for sensor in inputs {
+            let Some(raw) = read_trimmed(&entry.join(format!("{sensor}_input"))) else {
+                continue;
+            };
+            let Ok(raw) = raw.parse::<i64>() else {
+                continue;
+            };
+            let value = if sensor.starts_with("temp") {
+                SensorValue::Temperature(raw as f32 / 1000.0)
+            } else {
+                SensorValue::Fan(raw.max(0) as u32)
+            };
+            let label = read_trimmed(&entry.join(format!("{sensor}_label"))).unwrap_or(sensor);
+            readings.push(SensorReading {
+                label: format!("{device}/{label}"),
+                value,
+            });
+        }
+    }
+
+    // thermal zones are optional, e.g. they are missing in containers
+    if let Ok(entries) = class_entries(sysfs_root, "thermal") {
+        for entry in entries {
+            let Some(zone) = entry
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            if !zone.starts_with("thermal_zone") {
+                continue;
+            }
+            let Some(Ok(raw)) = read_trimmed(&entry.join("temp")).map(|raw| raw.parse::<i64>())
+            else {
+                continue;
+            };
+            let kind = read_trimmed(&entry.join("type")).unwrap_or_default();
+            readings.push(SensorReading {
+                label: format!("{kind}/{zone}"),
+                value: SensorValue::Temperature(raw as f32 / 1000.0),
+            });
+        }
+    }
+
+    Ok(readings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_power_supplies() -> anyhow::Result<()> {
+        let info = read_power_supplies(Path::new("test_data/sysfs"))?;
+        assert!(info.ac_online);
+        assert_eq!(
+            info.batteries,
+            vec![BatteryInfo {
+                name: "BAT1".to_string(),
+                capacity: 42,
+                charging: true,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_sensors() -> anyhow::Result<()> {
+        let readings = read_sensors(Path::new("test_data/sysfs"))?;
+        let labels: Vec<&str> = readings
+            .iter()
+            .map(|reading| reading.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "k10temp/Tctl",
+                "cros_ec/fan1",
+                "cros_ec/temp1",
+                "cros_ec/Battery",
+                "acpitz/thermal_zone0"
+            ]
+        );
+        assert_eq!(readings[0].value, SensorValue::Temperature(65.25));
+        assert_eq!(readings[1].value, SensorValue::Fan(3200));
+        assert!(readings[0].matches("Tctl"));
+        assert!(readings[0].matches("k10temp/Tctl"));
+        assert!(!readings[0].matches("k10temp"));
+        Ok(())
+    }
+}
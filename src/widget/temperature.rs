@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail};
+use image::GrayImage;
+
+use crate::config::widget_dto::{TemperatureStyle, TemperatureWidgetDto};
+use crate::gfx::font::{draw_number, GLYPH_HEIGHT};
+use crate::gfx::graph::draw_columns;
+use crate::gfx::{blank_frame, FULL};
+use crate::hw::HEIGHT;
+use crate::widget::sysfs::{read_sensors, SensorReading, SensorValue};
+use crate::widget::Widget;
+
+/// Temperatures and fan speeds from hwmon and thermal zones. The whole frame blinks
+/// while any temperature is at or above the warning threshold.
+#[derive(Debug)]
+pub struct TemperatureWidget {
+    sysfs_root: PathBuf,
+    sensors: Vec<String>,
+    style: TemperatureStyle,
+    min: i32,
+    max: i32,
+    warning: i32,
+    max_rpm: u32,
+    tick: u32,
+}
+
+/// Stacked numbers that fit on the matrix, one blank row apart.
+const MAX_DIGITS_SENSORS: usize = (HEIGHT + 1) / (GLYPH_HEIGHT as usize + 2);
+
+impl TryFrom<TemperatureWidgetDto> for TemperatureWidget {
+    type Error = anyhow::Error;
+
+    fn try_from(value: TemperatureWidgetDto) -> Result<Self, Self::Error> {
+        if value.style == TemperatureStyle::Digits && value.sensors.len() > MAX_DIGITS_SENSORS {
+            bail!(
+                "The digits style shows at most {MAX_DIGITS_SENSORS} sensors, {} are configured",
+                value.sensors.len()
+            );
+        }
+        Ok(TemperatureWidget {
+            sysfs_root: value.sysfs_root,
+            sensors: value.sensors,
+            style: value.style,
+            min: value.min,
+            max: value.max,
+            warning: value.warning,
+            max_rpm: value.max_rpm,
+            tick: 0,
+        })
+    }
+}
+
+impl TemperatureWidget {
+    /// Picks the configured sensors in order, or the first temperature if none are configured.
+    fn select(&self, readings: Vec<SensorReading>) -> anyhow::Result<Vec<SensorValue>> {
+        if self.sensors.is_empty() {
+            return readings
+                .into_iter()
+                .find(|reading| matches!(reading.value, SensorValue::Temperature(_)))
+                .map(|reading| vec![reading.value])
+                .ok_or_else(|| anyhow!("No temperature sensors found"));
+        }
+
+        self.sensors
+            .iter()
+            .map(|label| {
+                readings
+                    .iter()
+                    .find(|reading| reading.matches(label))
+                    .map(|reading| reading.value)
+                    .ok_or_else(|| anyhow!("Sensor {label} not found"))
+            })
+            .collect()
+    }
+
+    fn normalize(&self, value: SensorValue) -> f32 {
+        match value {
+            SensorValue::Temperature(celsius) => {
+                let range = (self.max - self.min).max(1) as f32;
+                (celsius - self.min as f32) / range
+            }
+            SensorValue::Fan(rpm) => rpm as f32 / self.max_rpm.max(1) as f32,
+        }
+    }
+
+    fn draw(&self, values: &[SensorValue]) -> GrayImage {
+        let mut img = blank_frame();
+
+        let warning = values.iter().any(|value| {
+            matches!(value, SensorValue::Temperature(celsius) if *celsius >= self.warning as f32)
+        });
+        if warning && self.tick % 2 == 1 {
+            return img;
+        }
+
+        match self.style {
+            TemperatureStyle::Thermometer => {
+                let values: Vec<f32> = values.iter().map(|&value| self.normalize(value)).collect();
+                draw_columns(&mut img, &values);
+            }
+            TemperatureStyle::Digits => {
+                for (index, value) in values.iter().enumerate() {
+                    let number = match *value {
+                        SensorValue::Temperature(celsius) => celsius.round().max(0.0) as u32,
+                        SensorValue::Fan(rpm) => rpm / 100,
+                    };
+                    draw_number(
+                        &mut img,
+                        1 + index as i32 * (GLYPH_HEIGHT + 2),
+                        number,
+                        FULL,
+                    );
+                }
+            }
+        }
+
+        img
+    }
+}
+
+impl Widget for TemperatureWidget {
+    fn render(&mut self) -> anyhow::Result<GrayImage> {
+        let values = self.select(read_sensors(&self.sysfs_root)?)?;
+        let img = self.draw(&values);
+        self.tick = self.tick.wrapping_add(1);
+        Ok(img)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Luma;
+
+    use super::*;
+    use crate::gfx::font::draw_text;
+    use crate::hw::WIDTH;
+
+    fn widget(sensors: &[&str], style: TemperatureStyle) -> anyhow::Result<TemperatureWidget> {
+        TemperatureWidget::try_from(TemperatureWidgetDto {
+            sysfs_root: PathBuf::from("test_data/sysfs"),
+            sensors: sensors.iter().map(|label| label.to_string()).collect(),
+            style,
+            min: 30,
+            max: 100,
+            warning: 85,
+            max_rpm: 6400,
+        })
+    }
+
+    #[test]
+    fn test_digits() -> anyhow::Result<()> {
+        let img = widget(&["Tctl", "fan1"], TemperatureStyle::Digits)?.render()?;
+
+        let mut expected = blank_frame();
+        draw_text(&mut expected, 1, 1, "65", FULL);
+        draw_text(&mut expected, 1, 8, "32", FULL);
+        assert_eq!(img, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_thermometer() -> anyhow::Result<()> {
+        let img = widget(&["acpitz/thermal_zone0"], TemperatureStyle::Thermometer)?.render()?;
+
+        // (50 - 30) / 70 of 34 rows
+        let lit = (0..34)
+            .filter(|&y| img.get_pixel(4, y) == &Luma([FULL]))
+            .count();
+        assert_eq!(lit, 9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_warning_blinks() -> anyhow::Result<()> {
+        let mut widget = widget(&["Battery"], TemperatureStyle::Digits)?;
+        let on = widget.render()?;
+        let off = widget.render()?;
+        assert!(on.pixels().any(|&Luma([p])| p > 0));
+        assert!(off.pixels().all(|&Luma([p])| p == 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_sensor() -> anyhow::Result<()> {
+        assert!(widget(&["gpu"], TemperatureStyle::Digits)?
+            .render()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_digits_fit() -> anyhow::Result<()> {
+        let img = widget(&["Tctl"; MAX_DIGITS_SENSORS], TemperatureStyle::Digits)?.render()?;
+        assert!((0..WIDTH as u32).any(|x| img.get_pixel(x, 33) == &Luma([FULL])));
+
+        let sensors = ["Tctl"; MAX_DIGITS_SENSORS + 1];
+        assert!(widget(&sensors, TemperatureStyle::Digits).is_err());
+        assert!(widget(&sensors, TemperatureStyle::Thermometer).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::anyhow;
+use image::GrayImage;
+
+use crate::config::widget_dto::{DiskWidgetDto, NetworkWidgetDto};
+use crate::gfx::blank_frame;
+use crate::gfx::graph::{draw_sparklines, History, Scale};
+use crate::widget::procfs::{read_diskstats, read_net_dev, IoCounters};
+use crate::widget::Widget;
+
+#[derive(Debug)]
+enum Source {
+    Network {
+        procfs_root: PathBuf,
+        interface: Option<String>,
+    },
+    Disk {
+        procfs_root: PathBuf,
+        sysfs_root: PathBuf,
+        device: Option<String>,
+    },
+}
+
+impl Source {
+    fn select(
+        counters: Vec<IoCounters>,
+        name: Option<&str>,
+        skip: impl Fn(&IoCounters) -> bool,
+    ) -> anyhow::Result<[u64; 2]> {
+        if let Some(name) = name {
+            let counter = counters
+                .iter()
+                .find(|counter| counter.name == name)
+                .ok_or_else(|| anyhow!("{name} not found"))?;
+            return Ok([counter.rx, counter.tx]);
+        }
+
+        Ok(counters
+            .iter()
+            .filter(|counter| !skip(counter))
+            .fold([0, 0], |[rx, tx], counter| {
+                [rx + counter.rx, tx + counter.tx]
+            }))
+    }
+
+    fn read(&self) -> anyhow::Result<[u64; 2]> {
+        match self {
+            Source::Network {
+                procfs_root,
+                interface,
+            } => Self::select(
+                read_net_dev(procfs_root)?,
+                interface.as_deref(),
+                |counter| counter.name == "lo",
+            ),
+            Source::Disk {
+                procfs_root,
+                sysfs_root,
+                device,
+            } => Self::select(read_diskstats(procfs_root)?, device.as_deref(), |counter| {
+                // virtual devices, device mapper and md arrays that count the I/O of their
+                // member disks again, and partitions that are already accounted in their disk
+                ["loop", "ram", "zram", "dm-", "md"]
+                    .iter()
+                    .any(|prefix| counter.name.starts_with(prefix))
+                    || sysfs_root
+                        .join("class/block")
+                        .join(&counter.name)
+                        .join("partition")
+                        .exists()
+            }),
+        }
+    }
+}
+
+/// Two scrolling sparklines side by side: rx/tx for interfaces, read/write for block devices.
+/// Both share the same auto-ranged maximum, which is the peak rate in the visible history.
+#[derive(Debug)]
+pub struct ThroughputWidget {
+    source: Source,
+    scale: Scale,
+    previous: Option<(Instant, [u64; 2])>,
+    histories: [History; 2],
+}
+
+impl From<NetworkWidgetDto> for ThroughputWidget {
+    fn from(value: NetworkWidgetDto) -> Self {
+        Self::new(
+            Source::Network {
+                procfs_root: value.procfs_root,
+                interface: value.interface,
+            },
+            value.scale,
+        )
+    }
+}
+
+impl From<DiskWidgetDto> for ThroughputWidget {
+    fn from(value: DiskWidgetDto) -> Self {
+        Self::new(
+            Source::Disk {
+                procfs_root: value.procfs_root,
+                sysfs_root: value.sysfs_root,
+                device: value.device,
+            },
+            value.scale,
+        )
+    }
+}
+
+impl ThroughputWidget {
+    fn new(source: Source, scale: Scale) -> Self {
+        ThroughputWidget {
+            source,
+            scale,
+            previous: None,
+            histories: Default::default(),
+        }
+    }
+
+    fn sample(&mut self, now: Instant, counters: [u64; 2]) {
+        if let Some((then, previous)) = self.previous {
+            let elapsed = now.duration_since(then).as_secs_f32();
+            for (index, history) in self.histories.iter_mut().enumerate() {
+                let delta = counters[index].saturating_sub(previous[index]) as f32;
+                history.push(if elapsed > 0.0 { delta / elapsed } else { 0.0 });
+            }
+        }
+        self.previous = Some((now, counters));
+    }
+
+    fn draw(&self) -> GrayImage {
+        let max = self.histories.iter().map(History::max).fold(0.0, f32::max);
+        let series: Vec<Vec<f32>> = self
+            .histories
+            .iter()
+            .map(|history| {
+                history
+                    .iter()
+                    .map(|rate| self.scale.normalize(rate, max))
+                    .collect()
+            })
+            .collect();
+
+        let mut img = blank_frame();
+        draw_sparklines(&mut img, &series);
+        img
+    }
+}
+
+impl Widget for ThroughputWidget {
+    fn render(&mut self) -> anyhow::Result<GrayImage> {
+        let counters = self.source.read()?;
+        self.sample(Instant::now(), counters);
+        Ok(self.draw())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use image::Luma;
+
+    use super::*;
+    use crate::gfx::FULL;
+
+    fn network(interface: Option<&str>, scale: Scale) -> ThroughputWidget {
+        ThroughputWidget::from(NetworkWidgetDto {
+            procfs_root: PathBuf::from("test_data/procfs"),
+            interface: interface.map(str::to_string),
+            scale,
+        })
+    }
+
+    #[test]
+    fn test_read_sources() -> anyhow::Result<()> {
+        assert_eq!(
+            network(None, Scale::Linear).source.read()?,
+            [1003000, 251000]
+        );
+        assert_eq!(
+            network(Some("enp0s1"), Scale::Linear).source.read()?,
+            [3000, 1000]
+        );
+        assert!(network(Some("eth9"), Scale::Linear).source.read().is_err());
+
+        let disk = |device: Option<&str>| {
+            ThroughputWidget::from(DiskWidgetDto {
+                procfs_root: PathBuf::from("test_data/procfs"),
+                sysfs_root: PathBuf::from("test_data/sysfs"),
+                device: device.map(str::to_string),
+                scale: Scale::Linear,
+            })
+        };
+        // nvme0n1, sda and sdaa; not their partitions, dm-0 or md0
+        assert_eq!(disk(None).source.read()?, [2340 * 512, 1120 * 512]);
+        assert_eq!(disk(Some("dm-0")).source.read()?, [300 * 512, 100 * 512]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_range() {
+        let mut widget = network(None, Scale::Linear);
+        let start = Instant::now();
+        widget.sample(start, [0, 0]);
+        widget.sample(start + Duration::from_secs(1), [1000, 500]);
+        widget.sample(start + Duration::from_secs(2), [1500, 500]);
+
+        let img = widget.draw();
+        // the peak rx rate fills the whole 4-pixel strip
+        assert_eq!(img.get_pixel(3, 32), &Luma([FULL]));
+        // tx was half of the peak, then idle
+        assert_eq!(img.get_pixel(6, 32), &Luma([FULL]));
+        assert_eq!(img.get_pixel(7, 32), &Luma([0]));
+        assert_eq!(img.get_pixel(5, 33), &Luma([0]));
+        // rx dropped to half of the peak
+        assert_eq!(img.get_pixel(1, 33), &Luma([FULL]));
+        assert_eq!(img.get_pixel(2, 33), &Luma([0]));
+    }
+
+    #[test]
+    fn test_log_scale() {
+        assert_eq!(Scale::Log.normalize(0.0, 1000.0), 0.0);
+        assert_eq!(Scale::Log.normalize(1000.0, 1000.0), 1.0);
+        assert!(Scale::Log.normalize(31.0, 1000.0) > 0.45);
+        assert!(Scale::Linear.normalize(31.0, 1000.0) < 0.05);
+    }
+}